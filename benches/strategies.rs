@@ -0,0 +1,84 @@
+//! Compares the black square placement strategies on the same seeds.
+//!
+//! Run with `cargo bench --bench strategies`.
+
+use std::time::{Duration, Instant};
+
+use kalypsi::{
+    generate::{GeneratorConfig, GridGenerator, Preset, Strategy},
+    validity, BoolMatrix, TriBoolMatrix,
+};
+
+const SEEDS: std::ops::Range<u64> = 0..10;
+
+/// An empty grid with no letters.
+#[derive(Clone, Copy)]
+struct Open(usize, usize);
+
+impl BoolMatrix for Open {
+    fn rows(self) -> usize {
+        self.1
+    }
+
+    fn cols(self) -> usize {
+        self.0
+    }
+
+    fn at(self, _: (usize, usize)) -> bool {
+        true
+    }
+}
+
+impl TriBoolMatrix for Open {
+    fn maybe_at(self, _: (usize, usize)) -> Option<bool> {
+        Some(true)
+    }
+}
+
+fn main() {
+    println!(
+        "{:<16} {:<22} {:>10} {:>10} {:>8} {:>8}",
+        "preset", "strategy", "mean ms", "energy", "valid", "met"
+    );
+
+    for preset in [Preset::Daily, Preset::Themeless, Preset::Sunday] {
+        let (width, height) = preset.size();
+        for strategy in Strategy::ALL {
+            let config = GeneratorConfig {
+                strategy,
+                ..preset.config()
+            };
+
+            let mut elapsed = Duration::ZERO;
+            let mut energy = 0.0;
+            let mut valid = 0;
+            let mut met = 0;
+            for seed in SEEDS {
+                let mut generator =
+                    GridGenerator::with_config(Open(width, height), config.clone()).with_seed(seed);
+
+                let start = Instant::now();
+                generator.place_blacks();
+                elapsed += start.elapsed();
+
+                let grid_energy = generator.energy();
+                energy += grid_energy;
+                met += usize::from(grid_energy == 0.0);
+                valid += usize::from(validity::check(&generator, config.min_word_len).is_valid());
+            }
+
+            let runs = SEEDS.count() as f32;
+            println!(
+                "{:<16} {:<22} {:>10.1} {:>10.3} {:>5}/{:<2} {:>5}/{:<2}",
+                preset.name(),
+                strategy.name(),
+                elapsed.as_secs_f32() * 1000.0 / runs,
+                energy / runs,
+                valid,
+                SEEDS.count(),
+                met,
+                SEEDS.count(),
+            );
+        }
+    }
+}
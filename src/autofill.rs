@@ -0,0 +1,356 @@
+use std::{collections::HashSet, fmt::Display};
+
+use crate::{
+    storage::{Entry, SavedCrossword},
+    words::{ScoredWord, WordList},
+};
+
+const NODE_LIMIT: usize = 20_000;
+/// How many partial fills are tried between calls to the progress callback.
+const PROGRESS_INTERVAL: usize = 1000;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct AutofillError {
+    /// The slots that ran out of candidates most often, which is where the
+    /// grid needs to change.
+    pub slots: Vec<Entry>,
+    /// Whether the search gave up before ruling out every fill.
+    pub gave_up: bool,
+}
+
+impl Display for AutofillError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let slots = self
+            .slots
+            .iter()
+            .map(|e| {
+                format!(
+                    "{} {}",
+                    e.number,
+                    if e.is_across { "Across" } else { "Down" }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        if self.gave_up {
+            write!(f, "gave up looking for a fill, {} was hardest", slots)
+        } else {
+            write!(f, "no fill exists for {}", slots)
+        }
+    }
+}
+
+struct Slot {
+    entry: Entry,
+    cells: Vec<usize>,
+}
+
+struct Search<'a, 'p> {
+    list: &'a WordList,
+    on_progress: &'p mut dyn FnMut(usize, u32, &[Option<u8>]),
+    slots: Vec<Slot>,
+    letters: Vec<Option<u8>>,
+    /// The letters of each rebus cell, which stay as they are.
+    rebus: Vec<Option<String>>,
+    assigned: Vec<bool>,
+    used: HashSet<String>,
+    score: u32,
+    best: Option<(u32, Vec<Option<u8>>)>,
+    failures: Vec<usize>,
+    nodes: usize,
+}
+
+impl<'a, 'p> Search<'a, 'p> {
+    fn pattern(&self, slot: usize) -> String {
+        self.slots[slot]
+            .cells
+            .iter()
+            .map(|&cell| match &self.rebus[cell] {
+                Some(rebus) => rebus.clone(),
+                None => self.letters[cell].map_or('?', char::from).to_string(),
+            })
+            .collect()
+    }
+
+    fn candidates(&self, slot: usize) -> Vec<&'a ScoredWord> {
+        self.list
+            .matches(&self.pattern(slot))
+            .into_iter()
+            .filter(|word| !self.used.contains(word.word.as_str()))
+            .collect()
+    }
+
+    fn search(&mut self) {
+        if self.nodes >= NODE_LIMIT {
+            return;
+        }
+        self.nodes += 1;
+
+        if self.nodes.is_multiple_of(PROGRESS_INTERVAL) {
+            if let Some((score, letters)) = &self.best {
+                (self.on_progress)(self.nodes, *score, letters);
+            }
+        }
+
+        // Pick the open slot with the fewest candidates, which also checks that
+        // the last word didn't leave any slot without one.
+        let mut next: Option<(usize, Vec<&'a ScoredWord>)> = None;
+        let mut bound = self.score;
+        for slot in 0..self.slots.len() {
+            if self.assigned[slot] {
+                continue;
+            }
+            let candidates = self.candidates(slot);
+            let Some(top) = candidates.first() else {
+                self.failures[slot] += 1;
+                return;
+            };
+            bound += top.score as u32;
+            if !matches!(&next, Some((_, c)) if c.len() <= candidates.len()) {
+                next = Some((slot, candidates));
+            }
+        }
+
+        if self.best.as_ref().is_some_and(|(best, _)| bound <= *best) {
+            return;
+        }
+
+        let Some((slot, candidates)) = next else {
+            self.best = Some((self.score, self.letters.clone()));
+            return;
+        };
+
+        for word in candidates {
+            let mut filled = Vec::new();
+            let mut offset = 0;
+            for &cell in self.slots[slot].cells.iter() {
+                if let Some(rebus) = &self.rebus[cell] {
+                    offset += rebus.chars().count();
+                    continue;
+                }
+                if self.letters[cell].is_none() {
+                    self.letters[cell] = Some(word.word.as_bytes()[offset]);
+                    filled.push(cell);
+                }
+                offset += 1;
+            }
+            self.assigned[slot] = true;
+            self.used.insert(word.word.clone());
+            self.score += word.score as u32;
+
+            self.search();
+
+            self.score -= word.score as u32;
+            self.used.remove(&word.word);
+            self.assigned[slot] = false;
+            for cell in filled {
+                self.letters[cell] = None;
+            }
+
+            if self.nodes >= NODE_LIMIT {
+                return;
+            }
+        }
+    }
+}
+
+/// Fills every open slot from `list`, keeping the letters already in the grid
+/// and never using a word twice. Searches for the highest scoring fill until it
+/// has tried `NODE_LIMIT` partial fills. Locked cells need no special care:
+/// their letters are kept like any others, and empty ones are open to fill.
+pub fn autofill(saved: &SavedCrossword, list: &WordList) -> Result<SavedCrossword, AutofillError> {
+    autofill_with(saved, list, |_, _, _| ())
+}
+
+/// Like `autofill`, but every `PROGRESS_INTERVAL` partial fills calls
+/// `on_progress` with the number tried, the best score so far and that fill,
+/// once there is one.
+pub fn autofill_with(
+    saved: &SavedCrossword,
+    list: &WordList,
+    mut on_progress: impl FnMut(usize, u32, SavedCrossword),
+) -> Result<SavedCrossword, AutofillError> {
+    let width = saved.width;
+    let rebus = (0..saved.cells.len())
+        .map(|i| {
+            saved
+                .letters((i % width, i / width))
+                .filter(|letters| letters.chars().count() > 1)
+                .map(|letters| letters.to_uppercase())
+        })
+        .collect::<Vec<_>>();
+    let letters = saved
+        .cells
+        .iter()
+        .zip(rebus.iter())
+        .map(|(cell, rebus)| match (cell, rebus) {
+            (Some(' ') | None, _) | (_, Some(_)) => None,
+            (Some(letter), None) => Some(letter.to_ascii_uppercase() as u8),
+        })
+        .collect::<Vec<_>>();
+    let slots = saved
+        .entries()
+        .into_iter()
+        .map(|entry| Slot {
+            entry,
+            cells: entry.cells().map(|(x, y)| x + y * width).collect(),
+        })
+        .collect::<Vec<_>>();
+
+    let mut on_progress = |nodes, score, letters: &[Option<u8>]| {
+        on_progress(nodes, score, with_letters(saved, letters))
+    };
+    let mut search = Search {
+        list,
+        on_progress: &mut on_progress,
+        assigned: vec![false; slots.len()],
+        failures: vec![0; slots.len()],
+        slots,
+        letters,
+        rebus,
+        used: HashSet::new(),
+        score: 0,
+        best: None,
+        nodes: 0,
+    };
+
+    // Words the constructor has already written in full stay as they are, even
+    // if they aren't in the list.
+    for slot in 0..search.slots.len() {
+        let pattern = search.pattern(slot);
+        if !pattern.contains('?') {
+            search.assigned[slot] = true;
+            search.used.insert(pattern);
+        }
+    }
+
+    search.search();
+
+    match search.best {
+        Some((_, letters)) => Ok(with_letters(saved, &letters)),
+        None => {
+            let most = search.failures.iter().copied().max().unwrap_or(0);
+            Err(AutofillError {
+                slots: search
+                    .slots
+                    .iter()
+                    .zip(search.failures.iter())
+                    .filter(|&(_, &failures)| failures == most && most > 0)
+                    .map(|(slot, _)| slot.entry)
+                    .collect(),
+                gave_up: search.nodes >= NODE_LIMIT,
+            })
+        }
+    }
+}
+
+fn with_letters(saved: &SavedCrossword, letters: &[Option<u8>]) -> SavedCrossword {
+    let mut filled = saved.clone();
+    for (cell, letter) in filled.cells.iter_mut().zip(letters) {
+        if let (Some(cell), Some(letter)) = (cell.as_mut(), letter) {
+            *cell = *letter as char;
+        }
+    }
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(rows: &[&str]) -> SavedCrossword {
+        SavedCrossword::new(
+            rows[0].len(),
+            rows.iter()
+                .flat_map(|row| row.chars())
+                .map(|c| if c == '#' { None } else { Some(c) })
+                .collect(),
+        )
+    }
+
+    fn rows(saved: &SavedCrossword) -> Vec<String> {
+        saved
+            .cells
+            .chunks(saved.width)
+            .map(|row| row.iter().map(|c| c.unwrap_or('#')).collect())
+            .collect()
+    }
+
+    fn words(saved: &SavedCrossword) -> HashSet<String> {
+        saved
+            .entries()
+            .iter()
+            .map(|entry| {
+                entry
+                    .cells()
+                    .map(|cell| saved.cell(cell).unwrap())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fills_open_grid_with_best_words() {
+        let list = WordList::parse("HAT;50\nERA;50\nMEN;50\nHEM;50\nARE;50\nTAN;50\nHEN;10\nTEN;5");
+        let filled = autofill(&grid(&["   ", "   ", "   "]), &list).unwrap();
+
+        assert_eq!(
+            words(&filled),
+            ["HAT", "ERA", "MEN", "HEM", "ARE", "TAN"]
+                .map(str::to_owned)
+                .into()
+        );
+    }
+
+    #[test]
+    fn keeps_entered_letters_and_avoids_duplicates() {
+        let list = WordList::parse("CAT;50\nATE;50\nTEN;50\nAGO;40\nTOE;40\nGOT;40");
+        let filled = autofill(&grid(&["C  ", "   ", "   "]), &list);
+
+        // Only CAT starts with C, and it can't be both 1 Across and 1 Down.
+        assert!(filled.is_err());
+
+        let list = WordList::parse("HAT;50\nERA;50\nMEN;50\nHEM;50\nARE;50\nTAN;50\nOAT;90");
+        let filled = autofill(&grid(&["H  ", "   ", "   "]), &list).unwrap();
+        assert_eq!(rows(&filled)[0].chars().next(), Some('H'));
+        assert_eq!(words(&filled).len(), 6);
+    }
+
+    #[test]
+    fn fills_locked_empty_cells() {
+        let list = WordList::parse("HAT;50\nERA;50\nMEN;50\nHEM;50\nARE;50\nTAN;50");
+        let mut saved = grid(&["H  ", "   ", "   "]);
+        saved.set_locked((0, 0), true);
+        saved.set_locked((1, 1), true);
+
+        let filled = autofill(&saved, &list).unwrap();
+        assert_eq!(rows(&filled), ["HAT", "ERA", "MEN"]);
+        assert!(filled.is_locked((0, 0)) && filled.is_locked((1, 1)));
+    }
+
+    #[test]
+    fn fills_around_a_rebus() {
+        let list = WordList::parse("BONE;50\nCONE;40\nTONY;30\nONE;90");
+        let mut saved = grid(&["# #", "   ", "# #"]);
+        saved.set_letters((1, 1), "ON");
+
+        let filled = autofill(&saved, &list).unwrap();
+        assert_eq!(filled.letters((1, 1)).as_deref(), Some("ON"));
+        let answers = filled
+            .entries()
+            .iter()
+            .map(|entry| filled.answer(entry))
+            .collect::<HashSet<_>>();
+        assert_eq!(answers, ["BONE", "CONE"].map(str::to_owned).into());
+    }
+
+    #[test]
+    fn reports_unfillable_slots() {
+        let list = WordList::parse("AB;50\nCD;50");
+        let error = autofill(&grid(&["  ", "# "]), &list).unwrap_err();
+
+        assert!(!error.gave_up);
+        assert!(!error.slots.is_empty());
+    }
+}
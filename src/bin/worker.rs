@@ -0,0 +1,32 @@
+//! The web worker that runs grid generation and autofill off the main thread.
+//! Each message is a JSON `Job`, answered with JSON `Report`s.
+
+use kalypsi::worker::{run, Job};
+use leptos::leptos_dom::console_warn;
+use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+use web_sys::{DedicatedWorkerGlobalScope, MessageEvent};
+
+fn main() {
+    console_error_panic_hook::set_once();
+
+    let scope = js_sys::global().unchecked_into::<DedicatedWorkerGlobalScope>();
+    let reply = scope.clone();
+    let on_message = Closure::<dyn Fn(MessageEvent)>::new(move |ev: MessageEvent| {
+        let job = match ev
+            .data()
+            .as_string()
+            .map(|json| serde_json::from_str::<Job>(&json))
+        {
+            Some(Ok(job)) => job,
+            Some(Err(e)) => return console_warn(&format!("unreadable job: {}", e)),
+            None => return console_warn("jobs must be sent as JSON strings"),
+        };
+
+        run(job, |report| match serde_json::to_string(&report) {
+            Ok(json) => _ = reply.post_message(&JsValue::from_str(&json)),
+            Err(e) => console_warn(&format!("failed to serialize report: {}", e)),
+        });
+    });
+    scope.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    on_message.forget();
+}
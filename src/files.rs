@@ -0,0 +1,39 @@
+use js_sys::{Array, Uint8Array};
+use leptos::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, BlobPropertyBag, Event, HtmlAnchorElement, HtmlInputElement, Url};
+
+pub fn download(filename: &str, mime: &str, bytes: &[u8]) {
+    let parts = Array::of1(&Uint8Array::from(bytes));
+    let mut options = BlobPropertyBag::new();
+    options.type_(mime);
+
+    let Ok(blob) = Blob::new_with_u8_array_sequence_and_options(&parts, &options) else {
+        return;
+    };
+    let Ok(url) = Url::create_object_url_with_blob(&blob) else {
+        return;
+    };
+
+    let anchor = document()
+        .create_element("a")
+        .expect("Expected to create anchor")
+        .unchecked_into::<HtmlAnchorElement>();
+    anchor.set_href(&url);
+    anchor.set_download(filename);
+    anchor.click();
+
+    _ = Url::revoke_object_url(&url);
+}
+
+/// Reads the file picked in the `<input type="file">` that fired `ev`.
+pub async fn read_picked_file(ev: Event) -> Option<(String, Vec<u8>)> {
+    let input = event_target::<HtmlInputElement>(&ev);
+    let file = input.files()?.get(0)?;
+    // Clear the input so that picking the same file again still fires change.
+    input.set_value("");
+
+    let buffer = JsFuture::from(file.array_buffer()).await.ok()?;
+    Some((file.name(), Uint8Array::new(&buffer).to_vec()))
+}
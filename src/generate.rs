@@ -1,43 +1,452 @@
-use std::ops::Range;
+use std::ops::{Range, RangeInclusive};
 
 use leptos::leptos_dom::console_log;
 use nanorand::{Rng, WyRand};
+use serde::{Deserialize, Serialize};
 
-use crate::{find_runs, BoolMatrix, TriBoolMatrix};
+use crate::{find_runs, validity, BoolMatrix, TriBoolMatrix};
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct GeneratorConfig {
+    pub avg_word_len: Range<f32>,
+    pub word_count: Range<usize>,
+    /// The share of cells that are black, from 0 to 1.
+    pub black_ratio: Range<f32>,
+    pub min_word_len: usize,
+    pub max_word_len: usize,
+    pub max_iters: usize,
+    #[serde(default)]
+    pub strategy: Strategy,
+    /// How many distinct patterns to keep for the user to choose from.
+    #[serde(default)]
+    pub candidates: usize,
+    /// Limits on how many words there are of particular lengths.
+    #[serde(default)]
+    pub length_targets: Vec<LengthTarget>,
+}
+
+/// A limit on the words whose length is in `lengths`, like "no more than 20%
+/// three-letter words" or "at least four of 7 letters or more".
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct LengthTarget {
+    pub lengths: RangeInclusive<usize>,
+    pub limit: Limit,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Limit {
+    Count(Range<usize>),
+    /// The share of all words, from 0 to 1.
+    Share(Range<f32>),
+}
+
+impl Limit {
+    /// The range as a count, or as a whole percentage for a share, where
+    /// `usize::MAX` means no upper limit.
+    pub fn bounds(&self) -> Range<usize> {
+        match self {
+            Limit::Count(range) => range.clone(),
+            Limit::Share(range) => {
+                let end = if range.end >= 1.0 {
+                    usize::MAX
+                } else {
+                    (range.end * 100.0).round() as usize
+                };
+                (range.start * 100.0).round() as usize..end
+            }
+        }
+    }
+
+    pub fn set_bounds(&mut self, bounds: Range<usize>) {
+        match self {
+            Limit::Count(range) => *range = bounds,
+            Limit::Share(range) => {
+                *range = bounds.start as f32 / 100.0..(bounds.end as f32 / 100.0).min(1.0)
+            }
+        }
+    }
+}
+
+impl LengthTarget {
+    /// How far `histogram` is from the target, as a share of all its words.
+    fn distance(&self, histogram: &[usize]) -> f32 {
+        let total = histogram.iter().sum::<usize>().max(1) as f32;
+        let count = histogram
+            .iter()
+            .enumerate()
+            .filter(|(len, _)| self.lengths.contains(len))
+            .map(|(_, &count)| count)
+            .sum::<usize>() as f32;
+
+        let share = match &self.limit {
+            Limit::Count(range) => range.start as f32 / total..range.end as f32 / total,
+            Limit::Share(range) => range.clone(),
+        };
+        if share.contains(&(count / total)) {
+            0.0
+        } else {
+            (share.start - count / total)
+                .abs()
+                .min((share.end - count / total).abs())
+        }
+    }
+}
+
+/// How many words there are of each length, indexed by length.
+pub fn histogram(runs: &[(usize, usize, usize, bool)]) -> Vec<usize> {
+    let longest = runs.iter().map(|run| run.2).max().unwrap_or(0);
+    let mut histogram = vec![0; longest + 1];
+    for run in runs {
+        histogram[run.2] += 1;
+    }
+    histogram
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        Preset::ALL[0].config()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Preset {
+    Daily,
+    Sunday,
+    Themeless,
+    Mini,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [
+        Preset::Daily,
+        Preset::Sunday,
+        Preset::Themeless,
+        Preset::Mini,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Daily => "Daily 15×15",
+            Preset::Sunday => "Sunday 21×21",
+            Preset::Themeless => "Themeless 15×15",
+            Preset::Mini => "Mini 5×5",
+        }
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        match self {
+            Preset::Daily | Preset::Themeless => (15, 15),
+            Preset::Sunday => (21, 21),
+            Preset::Mini => (5, 5),
+        }
+    }
+
+    pub fn config(&self) -> GeneratorConfig {
+        match self {
+            Preset::Daily => GeneratorConfig {
+                avg_word_len: 5.2..5.5,
+                word_count: 40..73,
+                black_ratio: 0.12..0.18,
+                min_word_len: 3,
+                max_word_len: 15,
+                max_iters: 10000,
+                strategy: Strategy::Restarts,
+                candidates: 6,
+                length_targets: Vec::new(),
+            },
+            Preset::Sunday => GeneratorConfig {
+                avg_word_len: 5.0..5.8,
+                word_count: 120..141,
+                black_ratio: 0.14..0.18,
+                min_word_len: 3,
+                max_word_len: 21,
+                max_iters: 5000,
+                strategy: Strategy::Annealing,
+                candidates: 6,
+                length_targets: vec![LengthTarget {
+                    lengths: 3..=3,
+                    limit: Limit::Share(0.0..0.3),
+                }],
+            },
+            Preset::Themeless => GeneratorConfig {
+                avg_word_len: 5.8..6.5,
+                word_count: 58..73,
+                black_ratio: 0.10..0.16,
+                min_word_len: 3,
+                max_word_len: 15,
+                max_iters: 10000,
+                strategy: Strategy::Restarts,
+                candidates: 6,
+                length_targets: vec![
+                    LengthTarget {
+                        lengths: 3..=3,
+                        limit: Limit::Share(0.0..0.2),
+                    },
+                    LengthTarget {
+                        lengths: 7..=15,
+                        limit: Limit::Count(4..usize::MAX),
+                    },
+                ],
+            },
+            Preset::Mini => GeneratorConfig {
+                avg_word_len: 3.5..5.0,
+                word_count: 8..13,
+                black_ratio: 0.0..0.2,
+                min_word_len: 3,
+                max_word_len: 5,
+                max_iters: 1000,
+                strategy: Strategy::Restarts,
+                candidates: 6,
+                length_targets: Vec::new(),
+            },
+        }
+    }
+}
+
+/// How `GridGenerator::place_blacks` searches for a pattern.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Strategy {
+    /// Places blacks greedily from scratch each iteration and keeps the best.
+    #[default]
+    Restarts,
+    /// Adds, removes and moves symmetric groups of blacks one at a time,
+    /// sometimes accepting a worse grid to get out of dead ends.
+    Annealing,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 2] = [Strategy::Restarts, Strategy::Annealing];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Strategy::Restarts => "Random restarts",
+            Strategy::Annealing => "Simulated annealing",
+        }
+    }
+}
+
+// Weights for the parts of `GridGenerator::energy` that aren't already scaled
+// by `Stats::distance`. Invalid grids must always score worse than valid ones.
+const LENGTH_WEIGHT: f32 = 2.0;
+const VALIDITY_WEIGHT: f32 = 10.0;
+const START_TEMPERATURE: f32 = 1.0;
+/// How many iterations pass between calls to the progress callback.
+const PROGRESS_INTERVAL: usize = 100;
+
+/// Measures of a grid that `GeneratorConfig` sets targets for.
+struct Stats {
+    avg_word_len: f32,
+    word_count: usize,
+    black_ratio: f32,
+    longest: usize,
+    histogram: Vec<usize>,
+}
+
+impl Stats {
+    /// How far outside the targets these are, relative to each target's size.
+    /// Zero when every target is met.
+    fn distance(&self, config: &GeneratorConfig) -> f32 {
+        fn outside(range: &Range<f32>, value: f32) -> f32 {
+            if range.contains(&value) {
+                0.0
+            } else {
+                let diff = (range.start - value).abs().min((range.end - value).abs());
+                diff / range.end.abs().max(1.0)
+            }
+        }
+
+        let word_count = config.word_count.start as f32..config.word_count.end as f32;
+        let longest = self.longest.saturating_sub(config.max_word_len) as f32;
+
+        outside(&config.avg_word_len, self.avg_word_len)
+            + outside(&word_count, self.word_count as f32)
+            + outside(&config.black_ratio, self.black_ratio)
+            + longest / config.max_word_len.max(1) as f32
+    }
+
+    /// How far the word lengths are from the config's length targets.
+    fn length_distance(&self, config: &GeneratorConfig) -> f32 {
+        config
+            .length_targets
+            .iter()
+            .map(|target| target.distance(&self.histogram))
+            .sum()
+    }
+}
+
+/// A generated pattern with the measures shown when choosing between them.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Candidate {
+    pub width: usize,
+    /// Whether each cell is black.
+    pub cells: Vec<bool>,
+    /// From `GridGenerator::energy`, so lower is better.
+    pub score: f32,
+    pub word_count: usize,
+    pub avg_word_len: f32,
+    /// How many words there are of each length, indexed by length.
+    pub histogram: Vec<usize>,
+}
+
+impl Candidate {
+    pub fn new(width: usize, cells: Vec<bool>, score: f32) -> Self {
+        let mut candidate = Self {
+            width,
+            cells,
+            score,
+            word_count: 0,
+            avg_word_len: 0.0,
+            histogram: Vec::new(),
+        };
+
+        let runs = find_runs(&candidate);
+        candidate.histogram = histogram(&runs);
+        candidate.word_count = runs.len();
+        candidate.avg_word_len =
+            runs.iter().map(|run| run.2).sum::<usize>() as f32 / runs.len().max(1) as f32;
+        candidate
+    }
+}
+
+impl BoolMatrix for &Candidate {
+    fn rows(self) -> usize {
+        self.cells.len() / self.width
+    }
+
+    fn cols(self) -> usize {
+        self.width
+    }
+
+    fn at(self, (x, y): (usize, usize)) -> bool {
+        x < self.width && self.cells.get(x + y * self.width) == Some(&false)
+    }
+}
+
+/// The best distinct patterns seen so far, best first, where patterns that are
+/// rotations or reflections of each other count as the same.
+struct Shortlist {
+    len: usize,
+    /// The score, the pattern in the form shared by all its rotations and
+    /// reflections, and the pattern itself.
+    entries: Vec<(f32, Vec<bool>, Vec<bool>)>,
+}
+
+impl Shortlist {
+    fn new(len: usize) -> Self {
+        Self {
+            len: len.max(1),
+            entries: Vec::new(),
+        }
+    }
+
+    fn offer(&mut self, score: f32, width: usize, cells: Vec<bool>) {
+        if self.entries.len() >= self.len
+            && self
+                .entries
+                .last()
+                .is_some_and(|(worst, ..)| *worst <= score)
+        {
+            return;
+        }
+
+        let key = canonical(width, &cells);
+        if let Some(i) = self.entries.iter().position(|(_, k, _)| *k == key) {
+            if self.entries[i].0 <= score {
+                return;
+            }
+            self.entries.remove(i);
+        }
+
+        let at = self.entries.partition_point(|(s, ..)| *s <= score);
+        self.entries.insert(at, (score, key, cells));
+        self.entries.truncate(self.len);
+    }
+
+    /// Whether there's nothing left to improve.
+    fn is_done(&self) -> bool {
+        self.entries.len() >= self.len && self.entries.iter().all(|(score, ..)| *score == 0.0)
+    }
+}
+
+/// The smallest of `cells` and its rotations and reflections, so that they all
+/// share it.
+fn canonical(width: usize, cells: &[bool]) -> Vec<bool> {
+    let height = cells.len() / width;
+    let (w, h) = (width - 1, height - 1);
+    let mut transforms: Vec<fn(usize, usize, usize, usize) -> (usize, usize)> = vec![
+        |x, y, _, _| (x, y),
+        |x, y, w, h| (w - x, h - y),
+        |x, y, w, _| (w - x, y),
+        |x, y, _, h| (x, h - y),
+    ];
+    if width == height {
+        transforms.extend([
+            (|x, y, _, _| (y, x)) as fn(usize, usize, usize, usize) -> (usize, usize),
+            |x, y, w, _| (w - y, x),
+            |x, y, _, h| (y, h - x),
+            |x, y, w, h| (w - y, h - x),
+        ]);
+    }
+
+    transforms
+        .into_iter()
+        .map(|transform| {
+            let mut out = vec![false; cells.len()];
+            for (i, &cell) in cells.iter().enumerate() {
+                let (x, y) = transform(i % width, i / width, w, h);
+                out[x + y * width] = cell;
+            }
+            out
+        })
+        .min()
+        .unwrap_or_default()
+}
 
 pub struct GridGenerator {
     symmetry: Symmetry,
+    config: GeneratorConfig,
+    shortlist: Shortlist,
     width: usize,
     cells: Vec<Cell>,
+    seed: u64,
     rand: WyRand,
 }
 
 impl GridGenerator {
     pub fn cells(self) -> Vec<bool> {
-        self.cells
-            .into_iter()
-            .map(|c| matches!(c, Cell::Black))
-            .collect()
+        blacks(&self.cells)
     }
 
     pub fn new<M>(matrix: M) -> Self
+    where
+        M: TriBoolMatrix,
+    {
+        Self::with_config(matrix, GeneratorConfig::default())
+    }
+
+    pub fn with_config<M>(matrix: M, config: GeneratorConfig) -> Self
     where
         M: TriBoolMatrix,
     {
         let width = matrix.cols();
         let height = matrix.rows();
+        let spacing = config.min_word_len as isize;
 
+        let seed = rand::random::<u64>();
         let mut new = Self {
-            symmetry: Symmetry::Point,
+            symmetry: Symmetry::default().fit(width, height),
+            shortlist: Shortlist::new(config.candidates),
+            config,
             width: matrix.cols(),
             cells: vec![Cell::default(); width * height],
-            rand: WyRand::new_seed(rand::random::<u64>()),
+            seed,
+            rand: WyRand::new_seed(seed),
         };
 
         for y in -1..=height as isize {
             for x in -1..=width as isize {
-                if y < 0 || x < 0 || x == width as isize || y == width as isize {
-                    new.block((x, y), 3);
+                if y < 0 || x < 0 || x == width as isize || y == height as isize {
+                    new.block((x, y), spacing);
                 }
             }
         }
@@ -49,7 +458,7 @@ impl GridGenerator {
                 if let Some(false) = maybe_at {
                     new.reserve((x, y));
                 } else if maybe_at.is_none() {
-                    new.block((x, y), 3);
+                    new.block((x, y), spacing);
                 }
             }
         }
@@ -57,72 +466,264 @@ impl GridGenerator {
         new
     }
 
-    // TODO change to be a target average word len
-    pub fn place_blacks(
-        &mut self,
-        target_avg_word_len: Range<f32>,
-        target_word_count: Range<usize>,
-    ) {
+    /// Falls back to the nearest mode that fits when `symmetry` needs a square
+    /// grid and this one isn't.
+    pub fn with_symmetry(mut self, symmetry: Symmetry) -> Self {
+        self.symmetry = symmetry.fit(self.width, self.height());
+        self
+    }
+
+    /// Replaces the random seed, so the same seed and grid always generate the
+    /// same pattern.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self.rand = WyRand::new_seed(seed);
+        self
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The best distinct patterns `place_blacks` found, best first.
+    pub fn candidates(&self) -> Vec<Candidate> {
+        self.shortlist
+            .entries
+            .iter()
+            .map(|(score, _, cells)| Candidate::new(self.width, cells.clone(), *score))
+            .collect()
+    }
+
+    fn stats(&self) -> Stats {
+        let runs = find_runs(self);
+        let blacks = self
+            .cells
+            .iter()
+            .filter(|c| matches!(c, Cell::Black))
+            .count();
+
+        Stats {
+            avg_word_len: runs.iter().fold(0, |acc, v| acc + v.2) as f32 / runs.len() as f32,
+            word_count: runs.len(),
+            black_ratio: blacks as f32 / self.cells.len() as f32,
+            longest: runs.iter().map(|v| v.2).max().unwrap_or(0),
+            histogram: histogram(&runs),
+        }
+    }
+
+    /// How far the grid is from the config's targets, weighing in words
+    /// outside the allowed lengths and anything `validity::check` finds. Zero
+    /// when the grid meets every target.
+    ///
+    /// Blacks are only placed until `Stats::distance` is met, so the length
+    /// targets only count here, when choosing between grids.
+    pub fn energy(&self) -> f32 {
+        let runs = find_runs(self);
+        let validity = validity::check(self, self.config.min_word_len);
+        let (min, max) = (self.config.min_word_len, self.config.max_word_len);
+
+        let outside_lengths = runs
+            .iter()
+            .map(|run| min.saturating_sub(run.2) + run.2.saturating_sub(max))
+            .sum::<usize>() as f32
+            / runs.len().max(1) as f32;
+        let problems =
+            validity.disconnected.len() + validity.unchecked.len() + validity.short.len();
+
+        let stats = self.stats();
+        stats.distance(&self.config)
+            + stats.length_distance(&self.config)
+            + LENGTH_WEIGHT * outside_lengths
+            + VALIDITY_WEIGHT * problems as f32
+    }
+
+    /// Places black squares until the grid meets the config's targets, keeping
+    /// the closest attempt if none do within its iteration budget.
+    pub fn place_blacks(&mut self) {
+        self.place_blacks_with(|_, _, _| ());
+    }
+
+    /// Like `place_blacks`, but every `PROGRESS_INTERVAL` iterations calls
+    /// `on_progress` with the iteration, the best score so far (lower is
+    /// better) and the black cells of the grid with that score.
+    pub fn place_blacks_with(&mut self, mut on_progress: impl FnMut(usize, f32, Vec<bool>)) {
+        match self.config.strategy {
+            Strategy::Restarts => self.restart(&mut on_progress),
+            Strategy::Annealing => self.anneal(&mut on_progress),
+        }
+
+        let energy = self.energy();
+        self.shortlist
+            .offer(energy, self.width, blacks(&self.cells));
+    }
+
+    /// Scores each attempt by `energy`, so invalid attempts lose to valid ones.
+    fn restart(&mut self, on_progress: &mut dyn FnMut(usize, f32, Vec<bool>)) {
         let start = self.cells.clone();
-        let mut best = None;
-        let mut best_avg_len = 0.0;
-        let mut best_avg_len_diff = f32::MAX;
-        let mut best_word_count = 0;
-        let mut best_word_count_diff = usize::MAX;
-        let max_iters = 10000;
+        let mut best: Option<(f32, Vec<Cell>)> = None;
         let mut iter = 0;
 
-        while !target_word_count.contains(&best_word_count)
-            || !target_avg_word_len.contains(&best_avg_len)
-        {
-            if iter > max_iters {
+        while iter < self.config.max_iters {
+            iter += 1;
+
+            let mut distance = self.stats().distance(&self.config);
+            while distance > 0.0 && self.choose_next_black().is_ok() {
+                distance = self.stats().distance(&self.config);
+            }
+
+            let energy = self.energy();
+            self.shortlist
+                .offer(energy, self.width, blacks(&self.cells));
+            if !matches!(&best, Some((best, _)) if *best < energy) {
+                best = Some((energy, std::mem::take(&mut self.cells)));
+            }
+            self.cells = start.clone();
+
+            if self.shortlist.is_done() {
                 break;
             }
 
-            let mut avg_len = f32::MAX;
-            let mut current_word_count = 0;
+            if iter.is_multiple_of(PROGRESS_INTERVAL) {
+                if let Some((energy, cells)) = &best {
+                    on_progress(iter, *energy, blacks(cells));
+                }
+            }
+        }
+
+        console_log(&format!("iter: {}", iter));
+
+        self.cells = best.map_or(start, |(_, cells)| cells);
+    }
 
+    /// Starts from one greedy attempt and improves it with local moves, taking
+    /// a worse grid with a chance that shrinks as the iterations run out.
+    fn anneal(&mut self, on_progress: &mut dyn FnMut(usize, f32, Vec<bool>)) {
+        // Blacks from the original grid and cells with letters stay as they are.
+        let fixed = self
+            .cells
+            .iter()
+            .map(|cell| {
+                !matches!(
+                    cell,
+                    Cell::White {
+                        reserved: false,
+                        ..
+                    }
+                )
+            })
+            .collect::<Vec<_>>();
+
+        while self.stats().distance(&self.config) > 0.0 && self.choose_next_black().is_ok() {}
+
+        let mut energy = self.energy();
+        let mut best = (energy, self.cells.clone());
+        let mut iter = 0;
+
+        while iter < self.config.max_iters && !self.shortlist.is_done() {
             iter += 1;
-            while !(target_avg_word_len.contains(&avg_len)
-                && target_word_count.contains(&current_word_count))
-                && self.choose_next_black().is_ok()
-            {
-                let runs = find_runs(&*self);
-                avg_len = runs.iter().fold(0, |acc, v| acc + v.2) as f32 / runs.len() as f32;
-                current_word_count = runs.len();
-            }
 
-            let diff = if target_avg_word_len.contains(&avg_len) {
-                0.0
-            } else {
-                (target_avg_word_len.start - avg_len)
-                    .abs()
-                    .min((target_avg_word_len.end - avg_len).abs())
+            let Some(undo) = self.local_move(&fixed) else {
+                continue;
             };
 
-            let word_count_diff = if target_word_count.contains(&current_word_count) {
-                0
+            let next = self.energy();
+            let temperature =
+                START_TEMPERATURE * (1.0 - iter as f32 / self.config.max_iters as f32);
+            if next <= energy || self.rand.generate::<f32>() < ((energy - next) / temperature).exp()
+            {
+                energy = next;
+                self.shortlist
+                    .offer(energy, self.width, blacks(&self.cells));
+                if energy < best.0 {
+                    best = (energy, self.cells.clone());
+                }
             } else {
-                target_word_count
-                    .start
-                    .abs_diff(current_word_count)
-                    .min(target_word_count.end.abs_diff(current_word_count))
-            };
+                for (i, cell) in undo.into_iter().rev() {
+                    self.cells[i] = cell;
+                }
+            }
 
-            if diff <= best_avg_len_diff && word_count_diff <= best_word_count_diff {
-                best = Some(std::mem::take(&mut self.cells));
-                best_avg_len = avg_len;
-                best_avg_len_diff = diff;
-                best_word_count = current_word_count;
-                best_word_count_diff = word_count_diff;
+            if iter.is_multiple_of(PROGRESS_INTERVAL) {
+                on_progress(iter, best.0, blacks(&best.1));
             }
+        }
 
-            self.cells = start.clone();
+        self.cells = best.1;
+    }
+
+    /// Adds, removes or moves one symmetric group of blacks, returning the
+    /// cells it changed and what they were before.
+    fn local_move(&mut self, fixed: &[bool]) -> Option<Vec<(usize, Cell)>> {
+        let free = |is_black: bool| {
+            (0..self.cells.len())
+                .filter(|&i| !fixed[i] && matches!(self.cells[i], Cell::Black) == is_black)
+                .collect::<Vec<_>>()
+        };
+        let whites = free(false);
+        let blacks = free(true);
+
+        let mut undo = Vec::new();
+        match self.rand.generate_range(0..3) {
+            0 => {
+                let i = *whites.get(self.rand.generate_range(0..whites.len().max(1)))?;
+                self.set_orbit(i, true, fixed, &mut undo)?;
+            }
+            1 => {
+                let i = *blacks.get(self.rand.generate_range(0..blacks.len().max(1)))?;
+                self.set_orbit(i, false, fixed, &mut undo)?;
+            }
+            _ => {
+                let i = *blacks.get(self.rand.generate_range(0..blacks.len().max(1)))?;
+                let (x, y) = self.calculate_coord(i);
+                let (dx, dy) = [(1, 0), (-1, 0), (0, 1), (0, -1)][self.rand.generate_range(0..4)];
+                let to = (x + dx, y + dy);
+                let moved = self.set_orbit(i, false, fixed, &mut undo).and_then(|()| {
+                    let in_grid = to.0 >= 0
+                        && to.1 >= 0
+                        && (to.0 as usize) < self.width
+                        && (to.1 as usize) < self.height();
+                    in_grid.then_some(())?;
+                    let to = to.0 as usize + to.1 as usize * self.width;
+                    self.set_orbit(to, true, fixed, &mut undo)
+                });
+                if moved.is_none() {
+                    for (i, cell) in undo.into_iter().rev() {
+                        self.cells[i] = cell;
+                    }
+                    return None;
+                }
+            }
         }
 
-        console_log(&format!("iter: {}", iter));
+        Some(undo)
+    }
+
+    /// Makes `index` and its symmetric partners black or white, unless any of
+    /// them is fixed or already that colour.
+    fn set_orbit(
+        &mut self,
+        index: usize,
+        black: bool,
+        fixed: &[bool],
+        undo: &mut Vec<(usize, Cell)>,
+    ) -> Option<()> {
+        let orbit = self
+            .reflect(self.calculate_coord(index))
+            .into_iter()
+            .map(|(x, y)| x as usize + y as usize * self.width)
+            .collect::<Vec<_>>();
+        if orbit
+            .iter()
+            .any(|&i| fixed[i] || matches!(self.cells[i], Cell::Black) == black)
+        {
+            return None;
+        }
 
-        self.cells = best.unwrap_or(start);
+        for i in orbit {
+            undo.push((i, self.cells[i]));
+            self.cells[i] = if black { Cell::Black } else { Cell::default() };
+        }
+        Some(())
     }
 
     fn choose_next_black(&mut self) -> Result<(), ()> {
@@ -142,17 +743,18 @@ impl GridGenerator {
                     (next_run.0 as isize, next_run.1 as isize + i)
                 };
 
+                let spacing = self.config.min_word_len;
                 let coords = self.reflect(coord);
                 let mut near = false;
                 for coord in coords.iter() {
                     let has = coords
                         .iter()
                         .filter(|c| !(c.0 == coord.0 && c.1 == coord.1))
-                        .any(|c| c.0 == coord.0 && c.1.abs_diff(coord.1) < 3);
+                        .any(|c| c.0 == coord.0 && c.1.abs_diff(coord.1) <= spacing);
                     let has2 = coords
                         .iter()
                         .filter(|c| !(c.0 == coord.0 && c.1 == coord.1))
-                        .any(|c| c.1 == coord.1 && c.0.abs_diff(coord.0) < 3);
+                        .any(|c| c.1 == coord.1 && c.0.abs_diff(coord.0) <= spacing);
                     near = near || has || has2;
                 }
 
@@ -179,7 +781,7 @@ impl GridGenerator {
 
             for coord in self.reflect(coord) {
                 // console_log(&format!("placing at {:?}", coord));
-                self.block(coord, 3);
+                self.block(coord, self.config.min_word_len as isize);
             }
             return Ok(());
         }
@@ -257,27 +859,10 @@ impl GridGenerator {
         }
     }
 
-    // todo turn into iterator
     fn reflect(&self, coord: (isize, isize)) -> Vec<(isize, isize)> {
-        match self.symmetry {
-            Symmetry::Quarter => vec![
-                coord,
-                (
-                    self.width as isize - coord.0 - 1,
-                    self.height() as isize - coord.1 - 1,
-                ),
-                (coord.0, self.height() as isize - coord.1 - 1),
-                (self.width as isize - coord.0 - 1, coord.1),
-            ],
-            Symmetry::Point => vec![
-                coord,
-                (
-                    self.width as isize - coord.0 - 1,
-                    self.height() as isize - coord.1 - 1,
-                ),
-            ],
-            Symmetry::None => vec![coord],
-        }
+        self.symmetry
+            .orbit(coord, self.width, self.height())
+            .collect()
     }
 
     fn height(&self) -> usize {
@@ -322,7 +907,11 @@ impl GridGenerator {
     }
 }
 
-impl<'a> BoolMatrix for &'a GridGenerator {
+fn blacks(cells: &[Cell]) -> Vec<bool> {
+    cells.iter().map(|c| matches!(c, Cell::Black)).collect()
+}
+
+impl BoolMatrix for &GridGenerator {
     fn rows(self) -> usize {
         self.height()
     }
@@ -333,7 +922,7 @@ impl<'a> BoolMatrix for &'a GridGenerator {
 
     fn at(self, (x, y): (usize, usize)) -> bool {
         self.get((x as isize, y as isize))
-            .map_or(false, |cell| matches!(cell, Cell::White { .. }))
+            .is_some_and(|cell| matches!(cell, Cell::White { .. }))
     }
 }
 
@@ -423,38 +1012,361 @@ impl Iterator for DirectionIter {
     }
 }
 
-#[derive(Clone, Copy)]
-enum Symmetry {
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Symmetry {
+    #[default]
     Point,
     Quarter,
+    MirrorLeftRight,
+    MirrorTopBottom,
+    Diagonal,
     None,
 }
 
 impl Symmetry {
-    fn rotate(&self, coord: (isize, isize), width: usize, height: isize) -> Option<(isize, isize)> {
+    pub const ALL: [Symmetry; 6] = [
+        Symmetry::Point,
+        Symmetry::Quarter,
+        Symmetry::MirrorLeftRight,
+        Symmetry::MirrorTopBottom,
+        Symmetry::Diagonal,
+        Symmetry::None,
+    ];
+
+    pub fn name(&self) -> &'static str {
         match self {
-            Symmetry::Point => Some((width as isize - coord.0 - 1, height - coord.1 - 1)),
-            Symmetry::Quarter => Some((width as isize - coord.0 - 1, height - coord.1 - 1)),
+            Symmetry::Point => "Rotational (180°)",
+            Symmetry::Quarter => "Rotational (90°)",
+            Symmetry::MirrorLeftRight => "Mirror left-right",
+            Symmetry::MirrorTopBottom => "Mirror top-bottom",
+            Symmetry::Diagonal => "Diagonal",
+            Symmetry::None => "None",
+        }
+    }
+
+    /// Quarter turns and diagonal reflections only map a grid onto itself when
+    /// it's square, so those fall back to the closest mode that does.
+    pub fn fit(self, width: usize, height: usize) -> Self {
+        match self {
+            Symmetry::Quarter if width != height => Symmetry::Point,
+            Symmetry::Diagonal if width != height => Symmetry::None,
+            symmetry => symmetry,
+        }
+    }
+
+    /// Moves `coord` one step around its orbit. Applying this repeatedly
+    /// visits every cell that must match `coord` before returning to it.
+    fn rotate(&self, coord: (isize, isize), width: usize, height: usize) -> Option<(isize, isize)> {
+        let (x, y) = coord;
+        let (width, height) = (width as isize, height as isize);
+        match self.fit(width as usize, height as usize) {
+            Symmetry::Point => Some((width - x - 1, height - y - 1)),
+            Symmetry::Quarter => Some((width - y - 1, x)),
+            Symmetry::MirrorLeftRight => Some((width - x - 1, y)),
+            Symmetry::MirrorTopBottom => Some((x, height - y - 1)),
+            Symmetry::Diagonal => Some((y, x)),
             Symmetry::None => None,
         }
     }
+
+    /// Every cell that must match `coord`, starting with `coord` itself.
+    pub fn orbit(&self, coord: (isize, isize), width: usize, height: usize) -> SymmetryIter {
+        SymmetryIter {
+            symmetry: *self,
+            start: coord,
+            next: Some(coord),
+            width,
+            height,
+        }
+    }
+}
+
+pub struct SymmetryIter {
+    symmetry: Symmetry,
+    start: (isize, isize),
+    next: Option<(isize, isize)>,
+    width: usize,
+    height: usize,
+}
+
+impl Iterator for SymmetryIter {
+    type Item = (isize, isize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.next?;
+
+        self.next = self
+            .symmetry
+            .rotate(next, self.width, self.height)
+            .filter(|&c| c != self.start);
+
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::SavedCrossword;
+
+    #[derive(Clone, Copy)]
+    struct Open(usize);
+
+    impl BoolMatrix for Open {
+        fn rows(self) -> usize {
+            self.0
+        }
+
+        fn cols(self) -> usize {
+            self.0
+        }
+
+        fn at(self, _: (usize, usize)) -> bool {
+            true
+        }
+    }
+
+    impl TriBoolMatrix for Open {
+        fn maybe_at(self, _: (usize, usize)) -> Option<bool> {
+            Some(true)
+        }
+    }
+
+    fn config() -> GeneratorConfig {
+        GeneratorConfig {
+            avg_word_len: 4.0..5.0,
+            word_count: 20..30,
+            black_ratio: 0.0..1.0,
+            min_word_len: 3,
+            max_word_len: 9,
+            max_iters: 10000,
+            strategy: Strategy::Restarts,
+            candidates: 1,
+            length_targets: Vec::new(),
+        }
+    }
+
+    fn generate(seed: u64) -> Vec<bool> {
+        let mut generator = GridGenerator::with_config(Open(9), config()).with_seed(seed);
+        generator.place_blacks();
+        generator.cells()
+    }
+
+    fn orbit(
+        symmetry: Symmetry,
+        coord: (isize, isize),
+        width: usize,
+        height: usize,
+    ) -> Vec<(isize, isize)> {
+        let mut orbit = symmetry.orbit(coord, width, height).collect::<Vec<_>>();
+        orbit.sort();
+        orbit
+    }
+
+    #[test]
+    fn same_seed_generates_same_grid() {
+        assert_eq!(generate(42), generate(42));
+        assert_eq!(generate(7), generate(7));
+        assert!(generate(42).contains(&true));
+    }
+
+    #[test]
+    fn different_seeds_generate_different_grids() {
+        assert!((0..8).any(|seed| generate(seed) != generate(42)));
+    }
+
+    #[test]
+    fn respects_word_length_limits() {
+        let config = GeneratorConfig {
+            avg_word_len: 0.0..100.0,
+            word_count: 0..1000,
+            min_word_len: 4,
+            max_word_len: 6,
+            max_iters: 200,
+            ..config()
+        };
+        let mut generator = GridGenerator::with_config(Open(11), config).with_seed(3);
+        generator.place_blacks();
+
+        let runs = find_runs(&generator);
+        assert!(!runs.is_empty());
+        assert!(
+            runs.iter().all(|run| (4..=6).contains(&run.2)),
+            "{:?}",
+            runs
+        );
+    }
+
+    #[test]
+    fn generated_grids_are_valid() {
+        for seed in 0..4 {
+            let mut generator = GridGenerator::with_config(Open(9), config()).with_seed(seed);
+            generator.place_blacks();
+
+            let validity = validity::check(&generator, 3);
+            assert!(validity.is_valid(), "seed {}: {:?}", seed, validity);
+        }
+    }
+
+    #[test]
+    fn rectangular_grids_are_valid() {
+        let config = GeneratorConfig {
+            word_count: 30..50,
+            ..config()
+        };
+        for (width, height) in [(9, 13), (13, 9)] {
+            let saved = SavedCrossword::with_size(width, height);
+            let mut generator = GridGenerator::with_config(&saved, config.clone()).with_seed(1);
+            generator.place_blacks();
+
+            let validity = validity::check(&generator, 3);
+            assert!(validity.is_valid(), "{}×{}: {:?}", width, height, validity);
+        }
+    }
+
+    #[test]
+    fn locked_cells_stay_white() {
+        let mut saved = SavedCrossword::with_size(9, 9);
+        let locked = [(0, 0), (4, 4), (8, 3), (2, 7)];
+        for cell in locked {
+            saved.set_locked(cell, true);
+        }
+
+        for seed in 0..4 {
+            let mut generator = GridGenerator::with_config(&saved, config()).with_seed(seed);
+            generator.place_blacks();
+            for cell in locked {
+                assert!((&generator).at(cell), "seed {}: {:?}", seed, cell);
+            }
+        }
+    }
+
+    #[test]
+    fn annealing_finds_valid_grids() {
+        let config = GeneratorConfig {
+            max_iters: 2000,
+            strategy: Strategy::Annealing,
+            ..config()
+        };
+        for seed in 0..4 {
+            let mut generator = GridGenerator::with_config(Open(9), config.clone()).with_seed(seed);
+            generator.place_blacks();
+
+            let validity = validity::check(&generator, 3);
+            assert!(validity.is_valid(), "seed {}: {:?}", seed, validity);
+        }
+    }
+
+    #[test]
+    fn length_targets_measure_counts_and_shares() {
+        // Ten words: six of 3 letters, two of 5 and two of 7.
+        let histogram = [0, 0, 0, 6, 0, 2, 0, 2];
+        let threes = |limit| LengthTarget {
+            lengths: 3..=3,
+            limit,
+        };
+
+        assert_eq!(threes(Limit::Share(0.0..0.7)).distance(&histogram), 0.0);
+        assert!((threes(Limit::Share(0.0..0.2)).distance(&histogram) - 0.4).abs() < 1e-6);
+        assert!((threes(Limit::Count(0..3)).distance(&histogram) - 0.3).abs() < 1e-6);
+
+        let long = LengthTarget {
+            lengths: 7..=usize::MAX,
+            limit: Limit::Count(4..usize::MAX),
+        };
+        assert!((long.distance(&histogram) - 0.2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn length_targets_steer_the_generator() {
+        let threes = |runs: &[(usize, usize, usize, bool)]| {
+            runs.iter().filter(|run| run.2 == 3).count() as f32 / runs.len() as f32
+        };
+        let with_targets = GeneratorConfig {
+            max_iters: 500,
+            length_targets: vec![LengthTarget {
+                lengths: 3..=3,
+                limit: Limit::Share(0.0..0.1),
+            }],
+            ..config()
+        };
+
+        let mut targeted = 0.0;
+        let mut untargeted = 0.0;
+        for seed in 0..4 {
+            let mut generator =
+                GridGenerator::with_config(Open(9), with_targets.clone()).with_seed(seed);
+            generator.place_blacks();
+            targeted += threes(&find_runs(&generator));
+
+            let mut generator = GridGenerator::with_config(Open(9), config()).with_seed(seed);
+            generator.place_blacks();
+            untargeted += threes(&find_runs(&generator));
+        }
+        assert!(targeted < untargeted, "{} >= {}", targeted, untargeted);
+    }
+
+    #[test]
+    fn candidates_are_distinct_under_symmetry() {
+        let config = GeneratorConfig {
+            candidates: 4,
+            ..config()
+        };
+        let mut generator = GridGenerator::with_config(Open(9), config).with_seed(5);
+        generator.place_blacks();
+
+        let candidates = generator.candidates();
+        assert_eq!(candidates.len(), 4);
+        assert!(candidates.windows(2).all(|w| w[0].score <= w[1].score));
+        for (i, a) in candidates.iter().enumerate() {
+            assert_eq!(a.histogram.iter().sum::<usize>(), a.word_count);
+            for b in &candidates[i + 1..] {
+                assert_ne!(canonical(9, &a.cells), canonical(9, &b.cells));
+            }
+        }
+    }
+
+    #[test]
+    fn canonical_form_ignores_rotation_and_reflection() {
+        let cells = [true, false, false, false, false, false, false, true, false];
+        let rotated = [false, false, true, true, false, false, false, false, false];
+        let mirrored = [false, false, true, false, false, false, false, true, false];
+
+        assert_eq!(canonical(3, &cells), canonical(3, &rotated));
+        assert_eq!(canonical(3, &cells), canonical(3, &mirrored));
+        assert_ne!(canonical(3, &cells), canonical(3, &[false; 9]));
+    }
+
+    #[test]
+    fn orbits_match_each_mode() {
+        assert_eq!(orbit(Symmetry::Point, (0, 1), 5, 4), [(0, 1), (4, 2)]);
+        assert_eq!(
+            orbit(Symmetry::Quarter, (0, 1), 5, 5),
+            [(0, 1), (1, 4), (3, 0), (4, 3)]
+        );
+        assert_eq!(
+            orbit(Symmetry::MirrorLeftRight, (1, 3), 5, 4),
+            [(1, 3), (3, 3)]
+        );
+        assert_eq!(
+            orbit(Symmetry::MirrorTopBottom, (1, 3), 5, 4),
+            [(1, 0), (1, 3)]
+        );
+        assert_eq!(orbit(Symmetry::Diagonal, (1, 3), 5, 5), [(1, 3), (3, 1)]);
+        assert_eq!(orbit(Symmetry::None, (1, 3), 5, 5), [(1, 3)]);
+    }
+
+    #[test]
+    fn centre_cells_are_their_own_orbit() {
+        for symmetry in Symmetry::ALL {
+            assert_eq!(orbit(symmetry, (2, 2), 5, 5), [(2, 2)], "{:?}", symmetry);
+        }
+        assert_eq!(orbit(Symmetry::MirrorLeftRight, (2, 0), 5, 4), [(2, 0)]);
+    }
+
+    #[test]
+    fn square_only_modes_fall_back_on_rectangles() {
+        assert_eq!(orbit(Symmetry::Quarter, (0, 1), 5, 4), [(0, 1), (4, 2)]);
+        assert_eq!(orbit(Symmetry::Diagonal, (0, 1), 5, 4), [(0, 1)]);
+    }
 }
-// pub struct SymmetryIter {
-//     symmetry: Symmetry,
-//     next: Option<(isize, isize)>,
-//     iteration: usize,
-//     width: usize,
-//     height: usize,
-// }
-//
-// impl Iterator for SymmetryIter {
-//     type Item = (isize, isize);
-//
-//     fn next(&mut self) -> Option<Self::Item> {
-//         if let Some(next) = self.next {}
-//
-//         self.iteration += 1;
-//
-//         next
-//     }
-// }
@@ -0,0 +1,86 @@
+use std::collections::VecDeque;
+
+use crate::{storage::SavedCrossword, Slot};
+
+const HISTORY_LIMIT: usize = 200;
+
+/// What kind of change a history step undoes. Consecutive edits of the same
+/// kind to the same word collapse into one step.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Edit {
+    Typing {
+        head: (usize, usize),
+        is_across: bool,
+    },
+    Clue {
+        head: (usize, usize),
+        is_across: bool,
+    },
+    Black,
+    Bar,
+    Fill,
+    Generate,
+    Template,
+    Resize,
+    Style,
+    Lock,
+    Clear,
+}
+
+impl Edit {
+    fn coalesces_with(&self, previous: &Edit) -> bool {
+        matches!(self, Edit::Typing { .. } | Edit::Clue { .. }) && self == previous
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Step {
+    pub saved: SavedCrossword,
+    pub selection: Option<Slot>,
+}
+
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct History {
+    undo: VecDeque<Step>,
+    redo: Vec<Step>,
+    last_edit: Option<Edit>,
+}
+
+impl History {
+    /// Whether `edit` needs a fresh step, or is a continuation of the
+    /// previous one.
+    pub fn needs_step(&self, edit: Edit) -> bool {
+        !matches!(self.last_edit, Some(last) if edit.coalesces_with(&last))
+    }
+
+    pub fn push(&mut self, edit: Edit, step: Step) {
+        if self.undo.len() == HISTORY_LIMIT {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(step);
+        self.redo.clear();
+        self.last_edit = Some(edit);
+    }
+
+    pub fn undo(&mut self, current: Step) -> Option<Step> {
+        let step = self.undo.pop_back()?;
+        self.redo.push(current);
+        self.last_edit = None;
+        Some(step)
+    }
+
+    pub fn redo(&mut self, current: Step) -> Option<Step> {
+        let step = self.redo.pop()?;
+        self.undo.push_back(current);
+        self.last_edit = None;
+        Some(step)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
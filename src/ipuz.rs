@@ -0,0 +1,562 @@
+use std::fmt::Display;
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    library::{Imported, Metadata},
+    storage::SavedCrossword,
+    Bars, CellStyle, Edge,
+};
+
+const VERSION: &str = "http://ipuz.org/v2";
+const KIND: &str = "http://ipuz.org/crossword#1";
+const BLOCK: &str = "#";
+const EMPTY: u64 = 0;
+/// The border width, in pixels, that bold cells are exported with.
+const BOLD_BORDER: u64 = 2;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum IpuzError {
+    Json(String),
+    NotACrossword,
+    BadDimensions,
+}
+
+impl Display for IpuzError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IpuzError::Json(e) => write!(f, "not valid ipuz: {}", e),
+            IpuzError::NotACrossword => write!(f, "not an ipuz crossword"),
+            IpuzError::BadDimensions => write!(f, "grid doesn't match its dimensions"),
+        }
+    }
+}
+
+/// Splits a trailing enumeration like "(3,4)" off a clue.
+fn split_enumeration(clue: &str) -> (&str, Option<&str>) {
+    let Some(open) = clue.strip_suffix(')').and_then(|c| c.rfind('(')) else {
+        return (clue, None);
+    };
+    let enumeration = &clue[open + 1..clue.len() - 1];
+    let is_enumeration = enumeration.starts_with(|c: char| c.is_ascii_digit())
+        && enumeration
+            .chars()
+            .all(|c| c.is_ascii_digit() || ",- .'".contains(c));
+
+    if is_enumeration {
+        (clue[..open].trim_end(), Some(enumeration))
+    } else {
+        (clue, None)
+    }
+}
+
+pub fn export(saved: &SavedCrossword, metadata: &Metadata) -> String {
+    let (width, height) = saved.size();
+    let entries = saved.entries();
+
+    let number_at = |coord| {
+        entries
+            .iter()
+            .find(|e| e.head == coord)
+            .map(|e| e.number as u64)
+    };
+
+    let rows = |f: &dyn Fn((usize, usize)) -> Value| {
+        (0..height)
+            .map(|y| (0..width).map(|x| f((x, y))).collect::<Vec<_>>())
+            .collect::<Vec<_>>()
+    };
+    let puzzle = rows(&|coord| match saved.cell(coord) {
+        None => json!(BLOCK),
+        Some(_) => {
+            let cell = json!(number_at(coord).unwrap_or(EMPTY));
+            let style = export_style(&saved.style(coord), saved.bars(coord));
+            if style.is_empty() {
+                cell
+            } else {
+                json!({ "cell": cell, "style": style })
+            }
+        }
+    });
+    let solution = rows(&|coord| match saved.letters(coord).as_deref() {
+        None => json!(BLOCK),
+        Some(" ") => Value::Null,
+        Some(letters) => json!(letters.to_uppercase()),
+    });
+
+    let clues = |is_across| {
+        entries
+            .iter()
+            .filter(|e| e.is_across == is_across)
+            .map(|e| {
+                let clue = saved.clue(e.head, e.is_across).unwrap_or_default();
+                match split_enumeration(clue) {
+                    (clue, Some(enumeration)) => json!({
+                        "number": e.number,
+                        "clue": clue,
+                        "enumeration": enumeration,
+                    }),
+                    (clue, None) => json!([e.number, clue]),
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let mut ipuz = json!({
+        "version": VERSION,
+        "kind": [KIND],
+        "dimensions": { "width": width, "height": height },
+        "block": BLOCK,
+        "empty": EMPTY,
+        "puzzle": puzzle,
+        "solution": solution,
+        "clues": {
+            "Across": clues(true),
+            "Down": clues(false),
+        },
+    });
+
+    let object = ipuz.as_object_mut().expect("ipuz is an object");
+    for (key, value) in [
+        ("title", &metadata.title),
+        ("author", &metadata.author),
+        ("copyright", &metadata.copyright),
+        ("notes", &metadata.notes),
+        ("publisher", &metadata.publisher),
+        ("editor", &metadata.editor),
+        ("date", &metadata.date),
+        ("origin", &metadata.origin),
+        ("intro", &metadata.intro),
+    ] {
+        if !value.is_empty() {
+            object.insert(key.to_owned(), json!(value));
+        }
+    }
+
+    serde_json::to_string_pretty(&ipuz).expect("ipuz serializes")
+}
+
+fn export_style(style: &CellStyle, bars: Bars) -> Map<String, Value> {
+    let mut spec = Map::new();
+    if !bars.is_plain() {
+        let sides = [(bars.right, "R"), (bars.bottom, "B")]
+            .into_iter()
+            .filter_map(|(barred, side)| barred.then_some(side))
+            .collect::<String>();
+        spec.insert("barred".to_owned(), json!(sides));
+    }
+    if style.circle {
+        spec.insert("shapebg".to_owned(), json!("circle"));
+    }
+    if let Some(shade) = &style.shade {
+        spec.insert("color".to_owned(), json!(shade.trim_start_matches('#')));
+    }
+    if style.bold {
+        spec.insert("border".to_owned(), json!(BOLD_BORDER));
+    }
+    spec
+}
+
+/// Reads the parts of an ipuz style spec that cells support, along with the
+/// edges it bars, and whether it had any others.
+fn import_style(spec: &Map<String, Value>) -> (CellStyle, Vec<Edge>, bool) {
+    let mut style = CellStyle::default();
+    let mut bars = Vec::new();
+    let mut partial = false;
+    for (key, value) in spec {
+        match (key.as_str(), value) {
+            ("barred", Value::String(sides)) => {
+                bars.extend(sides.chars().filter_map(|side| match side {
+                    'T' => Some(Edge::Top),
+                    'B' => Some(Edge::Bottom),
+                    'L' => Some(Edge::Left),
+                    'R' => Some(Edge::Right),
+                    _ => None,
+                }));
+            }
+            ("shapebg", Value::String(shape)) if shape == "circle" => style.circle = true,
+            ("color", Value::String(colour))
+                if colour
+                    .trim_start_matches('#')
+                    .chars()
+                    .all(|c| c.is_ascii_hexdigit()) =>
+            {
+                style.shade = Some(format!("#{}", colour.trim_start_matches('#')));
+            }
+            ("border", border) if border.as_u64().is_some_and(|b| b > 1) => style.bold = true,
+            _ => partial = true,
+        }
+    }
+    (style, bars, partial)
+}
+
+/// Returns the printable form of an ipuz cell value, which may be given bare
+/// or wrapped in an object alongside styling.
+fn cell_value(value: &Value) -> Option<&Value> {
+    match value {
+        Value::Object(cell) => cell.get("cell").or_else(|| cell.get("value")),
+        value => Some(value),
+    }
+}
+
+fn is_block(value: &Value, block: &str) -> bool {
+    match cell_value(value) {
+        Some(Value::String(s)) => s == block,
+        _ => false,
+    }
+}
+
+struct Warnings(Vec<String>);
+
+impl Warnings {
+    fn count(&mut self, count: usize, what: &str) {
+        if count > 0 {
+            self.0.push(format!("{} {}", count, what));
+        }
+    }
+}
+
+pub fn import(bytes: &[u8]) -> Result<Imported, IpuzError> {
+    let text = String::from_utf8_lossy(bytes);
+    // ipuz files are often served as JSONP, wrapped in `ipuz(...)`.
+    let text = text.trim();
+    let text = text
+        .strip_prefix("ipuz(")
+        .and_then(|t| t.trim_end_matches(';').strip_suffix(')'))
+        .unwrap_or(text);
+
+    let ipuz: Map<String, Value> =
+        serde_json::from_str(text).map_err(|e| IpuzError::Json(e.to_string()))?;
+
+    let is_crossword = ipuz
+        .get("kind")
+        .and_then(Value::as_array)
+        .is_some_and(|kinds| {
+            kinds
+                .iter()
+                .filter_map(Value::as_str)
+                .any(|k| k.starts_with("http://ipuz.org/crossword"))
+        });
+    if !is_crossword {
+        return Err(IpuzError::NotACrossword);
+    }
+
+    let dimension = |name| {
+        ipuz.get("dimensions")
+            .and_then(|d| d.get(name))
+            .and_then(Value::as_u64)
+            .map(|n| n as usize)
+            .filter(|&n| n > 0)
+            .ok_or(IpuzError::BadDimensions)
+    };
+    let width = dimension("width")?;
+    let height = dimension("height")?;
+
+    let grid = |key| -> Result<Vec<Value>, IpuzError> {
+        let Some(rows) = ipuz.get(key).and_then(Value::as_array) else {
+            return Ok(Vec::new());
+        };
+        if rows.len() != height {
+            return Err(IpuzError::BadDimensions);
+        }
+        rows.iter()
+            .map(|row| match row.as_array() {
+                Some(row) if row.len() == width => Ok(row.clone()),
+                _ => Err(IpuzError::BadDimensions),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(|rows| rows.concat())
+    };
+    let puzzle = grid("puzzle")?;
+    let solution = grid("solution")?;
+    if puzzle.is_empty() && solution.is_empty() {
+        return Err(IpuzError::BadDimensions);
+    }
+
+    let block = ipuz.get("block").and_then(Value::as_str).unwrap_or(BLOCK);
+    let string = |key| {
+        ipuz.get(key)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_owned()
+    };
+
+    let mut warnings = Warnings(Vec::new());
+
+    for key in ipuz.keys() {
+        let supported = matches!(
+            key.as_str(),
+            "version"
+                | "kind"
+                | "dimensions"
+                | "puzzle"
+                | "solution"
+                | "clues"
+                | "block"
+                | "empty"
+                | "title"
+                | "author"
+                | "copyright"
+                | "notes"
+                | "publisher"
+                | "date"
+                | "origin"
+                | "editor"
+                | "intro"
+                | "styles"
+        );
+        if !supported {
+            warnings.0.push(format!("ignored unsupported \"{}\"", key));
+        }
+    }
+
+    // Cells can name a style from the top-level "styles" instead of giving one.
+    let named_styles = ipuz.get("styles").and_then(Value::as_object);
+    let style_spec = |cell: &Value| match cell.get("style")? {
+        Value::Object(spec) => Some(spec.clone()),
+        Value::String(name) => named_styles?.get(name)?.as_object().cloned(),
+        _ => None,
+    };
+
+    let mut omitted = 0;
+    let mut partly_styled = 0;
+    let mut styles = Vec::new();
+    let mut rebus = Vec::new();
+    let cells = (0..width * height)
+        .map(|i| {
+            let puzzle = puzzle.get(i);
+            let solution = solution.get(i).unwrap_or(&Value::Null);
+
+            if let Some(spec) = puzzle.and_then(style_spec).or_else(|| style_spec(solution)) {
+                let (style, bars, partial) = import_style(&spec);
+                partly_styled += usize::from(partial);
+                styles.push(((i % width, i / width), style, bars));
+            }
+            if puzzle.is_some_and(Value::is_null) {
+                omitted += 1;
+                return None;
+            }
+            if puzzle.is_some_and(|p| is_block(p, block)) || is_block(solution, block) {
+                return None;
+            }
+
+            match cell_value(solution).and_then(Value::as_str) {
+                Some(letters) => {
+                    let letters = letters
+                        .chars()
+                        .filter(|c| c.is_alphanumeric())
+                        .collect::<String>()
+                        .to_uppercase();
+                    if letters.chars().count() > 1 {
+                        rebus.push(((i % width, i / width), letters.clone()));
+                    }
+                    Some(letters.chars().next().unwrap_or(' '))
+                }
+                None => Some(' '),
+            }
+        })
+        .collect();
+
+    warnings.count(omitted, "omitted cells were imported as blocks");
+    warnings.count(
+        partly_styled,
+        "styled cells were imported with only their circles, shading, borders and bars",
+    );
+
+    let mut saved = SavedCrossword::new(width, cells);
+    for (cell, letters) in rebus {
+        saved.set_letters(cell, &letters);
+    }
+    for (cell, style, bars) in styles {
+        saved.set_style(cell, style);
+        for edge in bars {
+            saved.add_bar(cell, edge);
+        }
+    }
+    let entries = saved.entries();
+
+    let numbered_differently = entries
+        .iter()
+        .filter(|e| match puzzle.get(e.head.0 + e.head.1 * width) {
+            Some(cell) => match cell_value(cell) {
+                Some(Value::Number(n)) => n.as_u64() != Some(e.number as u64),
+                Some(Value::String(s)) => s.parse::<usize>().ok() != Some(e.number),
+                _ => false,
+            },
+            None => false,
+        })
+        .count();
+    warnings.count(numbered_differently, "cells were renumbered");
+
+    let mut unmatched = 0;
+    let mut cross_references = 0;
+    if let Some(clues) = ipuz.get("clues").and_then(Value::as_object) {
+        for (direction, list) in clues {
+            // Directions may carry a display label, as in "Across:Horizontal".
+            let is_across = match direction.split(':').next() {
+                Some("Across") => true,
+                Some("Down") => false,
+                _ => {
+                    warnings
+                        .0
+                        .push(format!("ignored unsupported \"{}\" clues", direction));
+                    continue;
+                }
+            };
+
+            for clue in list.as_array().into_iter().flatten() {
+                let (number, text, enumeration) = match clue {
+                    Value::Array(pair) => (pair.first(), pair.get(1), None),
+                    Value::Object(clue) => {
+                        if clue.contains_key("cells") || clue.contains_key("continued") {
+                            cross_references += 1;
+                        }
+                        (
+                            clue.get("number"),
+                            clue.get("clue"),
+                            clue.get("enumeration"),
+                        )
+                    }
+                    text => (None, Some(text), None),
+                };
+                let number = match number {
+                    Some(Value::Number(n)) => n.as_u64().map(|n| n as usize),
+                    Some(Value::String(s)) => s.parse().ok(),
+                    _ => None,
+                };
+
+                let Some(entry) = entries
+                    .iter()
+                    .find(|e| e.is_across == is_across && Some(e.number) == number)
+                else {
+                    unmatched += 1;
+                    continue;
+                };
+
+                let mut text = text.and_then(Value::as_str).unwrap_or_default().to_owned();
+                if let Some(enumeration) = enumeration.and_then(Value::as_str) {
+                    text = format!("{} ({})", text, enumeration);
+                }
+                saved.set_clue(entry.head, entry.is_across, text);
+            }
+        }
+    }
+    warnings.count(unmatched, "clues didn't match an entry in the grid");
+    warnings.count(
+        cross_references,
+        "clues spanning several entries were imported as plain clues",
+    );
+
+    Ok(Imported {
+        saved,
+        metadata: Metadata {
+            title: string("title"),
+            author: string("author"),
+            copyright: string("copyright"),
+            notes: string("notes"),
+            publisher: string("publisher"),
+            editor: string("editor"),
+            date: string("date"),
+            origin: string("origin"),
+            intro: string("intro"),
+        },
+        warnings: warnings.0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_then_import_round_trips() {
+        let mut saved = SavedCrossword::new(
+            3,
+            vec![
+                Some('C'),
+                Some('A'),
+                Some('T'),
+                Some('A'),
+                None,
+                Some(' '),
+                Some('R'),
+                Some('O'),
+                Some('E'),
+            ],
+        );
+        // Splits 1 Across after its first letter.
+        saved.add_bar((1, 0), Edge::Left);
+        for (i, entry) in saved.entries().into_iter().enumerate() {
+            saved.set_clue(entry.head, entry.is_across, format!("Clue {} (3)", i));
+        }
+        let style = CellStyle {
+            circle: true,
+            shade: Some("#f9e2af".to_owned()),
+            bold: true,
+        };
+        saved.set_style((0, 0), style.clone());
+        let metadata = Metadata {
+            title: "Tiny".to_owned(),
+            author: "Setter".to_owned(),
+            publisher: "Press".to_owned(),
+            editor: "Editor".to_owned(),
+            date: "03/14/2023".to_owned(),
+            origin: "Kalypsi".to_owned(),
+            intro: "Read across first.".to_owned(),
+            ..Metadata::default()
+        };
+
+        let json = export(&saved, &metadata);
+        let imported = import(json.as_bytes()).unwrap();
+
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+        assert_eq!(imported.saved.cells, saved.cells);
+        assert_eq!(imported.saved.style((0, 0)), style);
+        assert!(imported.saved.style((1, 0)).is_plain());
+        assert_eq!(imported.saved.bars((0, 0)), saved.bars((0, 0)));
+        assert!(imported.saved.bars((0, 0)).right);
+        for entry in saved.entries() {
+            assert_eq!(
+                imported.saved.clue(entry.head, entry.is_across),
+                saved.clue(entry.head, entry.is_across)
+            );
+        }
+        assert_eq!(imported.metadata, metadata);
+    }
+
+    #[test]
+    fn import_reports_unsupported_features() {
+        let ipuz = r##"ipuz({
+            "version": "http://ipuz.org/v2",
+            "kind": ["http://ipuz.org/crossword#1"],
+            "dimensions": { "width": 2, "height": 2 },
+            "puzzle": [[{ "cell": 1, "style": { "shapebg": "circle" } }, 2], [3, "#"]],
+            "solution": [["AB", "C"], ["D", "#"]],
+            "clues": {
+                "Across": [[1, "First"]],
+                "Down": [{ "number": 1, "clue": "Second", "enumeration": "2" }],
+                "Diagonal": [[1, "Third"]]
+            },
+            "showenumerations": true,
+            "saved": [["A", null], [null, "#"]]
+        })"##;
+
+        let imported = import(ipuz.as_bytes()).unwrap();
+
+        assert_eq!(imported.saved.letters((0, 0)).as_deref(), Some("AB"));
+        assert!(imported.saved.style((0, 0)).circle);
+        assert_eq!(imported.saved.cell((1, 1)), None);
+        assert_eq!(imported.saved.clue((0, 0), false), Some("Second (2)"));
+        assert_eq!(imported.warnings.len(), 3, "{:?}", imported.warnings);
+    }
+
+    #[test]
+    fn enumerations_split_from_clues() {
+        assert_eq!(split_enumeration("Pet (3)"), ("Pet", Some("3")));
+        assert_eq!(split_enumeration("Big cat (4,3)"), ("Big cat", Some("4,3")));
+        assert_eq!(
+            split_enumeration("Pet (informal)"),
+            ("Pet (informal)", None)
+        );
+    }
+}
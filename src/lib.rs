@@ -2,36 +2,555 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 
-use std::{collections::HashMap, fmt::Display};
-
-use leptos::{html::Div, leptos_dom::console_log, *};
+use std::{collections::HashMap, fmt::Display, ops::Range};
+
+use leptos::{
+    html::{Div, Input, Li},
+    leptos_dom::{console_log, console_warn},
+    *,
+};
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use wasm_bindgen::JsCast;
-use web_sys::{Element, Event, FocusEvent, KeyboardEvent, MouseEvent};
-
-use crate::generate::GridGenerator;
-
+use wasm_bindgen::{prelude::Closure, JsCast};
+use web_sys::{
+    Element, FocusEvent, KeyboardEvent, MouseEvent, ScrollIntoViewOptions, ScrollLogicalPosition,
+};
+
+use crate::{
+    generate::{Candidate, GeneratorConfig, LengthTarget, Limit, Preset, Strategy, Symmetry},
+    history::{Edit, History, Step},
+    library::{Library, PuzzleDetails, PuzzleDetailsProps, PuzzleList, PuzzleListProps},
+    storage::SavedCrossword,
+    templates::{Template, TemplateLibrary, TemplateLibraryProps, Templates},
+    validity::Validity,
+    words::{Crossing, Dictionary, ScoredWord, WordList, WordListSettings, WordListSettingsProps},
+    worker::{Job, Outcome, Report, Running},
+};
+
+pub mod autofill;
+mod files;
 pub mod generate;
+mod history;
+pub mod ipuz;
+mod library;
+pub mod puz;
+mod storage;
+pub mod templates;
+pub mod validity;
+pub mod words;
+pub mod worker;
 // pub mod state;
 // pub mod util;
 
 const STORAGE_KEY: &str = "kalypsi";
 const DEFAULT_WIDTH: usize = 15;
 const DEFAULT_HEIGHT: usize = 15;
+/// The smallest and largest a grid's width or height can be.
+const MIN_SIZE: usize = 3;
+const MAX_SIZE: usize = 25;
+const SUGGESTION_LIMIT: usize = 50;
+const DEFAULT_SHADE: &str = "#f9e2af";
+const EDGE_MARGIN: f64 = 0.25;
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy)]
 struct Store {
     crossword: Crossword,
     selection: Selection,
+    dictionary: Dictionary,
+    status: RwSignal<Option<String>>,
+    last_seed: RwSignal<Option<u64>>,
+    generator: RwSignal<GeneratorConfig>,
+    validity: Memo<Validity>,
+    job: RwSignal<Option<JobProgress>>,
+    running: StoredValue<Option<Running>>,
+    /// The grid the running job started from, so that its outcome isn't
+    /// applied over edits made since.
+    job_start: StoredValue<Option<SavedCrossword>>,
+    candidates: RwSignal<Vec<Candidate>>,
+    /// Whether the caret cell is showing the editor for several letters.
+    rebus: RwSignal<bool>,
+    /// The colour cells are shaded with.
+    shade: RwSignal<String>,
+    /// Whether clicking near a cell's edge toggles the bar there.
+    editing_bars: RwSignal<bool>,
+}
+
+/// The last progress reported by the job running in the worker.
+#[derive(Clone, PartialEq, Debug)]
+struct JobProgress {
+    name: &'static str,
+    iteration: usize,
+    score: Option<f32>,
+    best: Option<Outcome>,
 }
 
+/// Every change to the puzzle or the selection goes through one of these, so
+/// that undo history is recorded in a single place.
+#[derive(Clone, PartialEq, Debug)]
 enum Action {
-    ClickCell(usize, usize),
+    ClickCell((usize, usize)),
+    EnterLetter(char),
+    OpenRebus,
+    /// Writes several letters into the caret cell and closes the rebus editor.
+    EnterRebus(String),
+    CloseRebus,
+    FillSlot(String),
+    Erase,
+    ToggleBlack,
+    ToggleBar {
+        cell: (usize, usize),
+        edge: Edge,
+    },
+    MoveCaret(Arrow),
+    SwitchDirection,
+    NextWord,
+    ClearSelection,
+    SelectWord {
+        head: (usize, usize),
+        is_across: bool,
+    },
+    SetClue {
+        head: (usize, usize),
+        is_across: bool,
+        clue: String,
+    },
+    SetSymmetry(Symmetry),
+    SetGeneratorConfig(GeneratorConfig),
+    /// Takes the preset's settings and resizes the grid to its size.
+    ChoosePreset(Preset),
+    RunGenerator {
+        seed: Option<u64>,
+    },
+    PickCandidate(usize),
+    ApplyTemplate(Template),
+    /// Marks the caret cell, or every cell of the active slot, unless they're
+    /// all marked already, in which case it clears them.
+    ToggleMark {
+        mark: Mark,
+        whole_slot: bool,
+    },
+    /// Locks the caret cell or the active slot, or unlocks them if they're
+    /// all locked already.
+    ToggleLock {
+        whole_slot: bool,
+    },
+    ClearUnlocked,
+    /// Adds a row or column along the edge, or removes the one there.
+    Resize {
+        edge: Edge,
+        grow: bool,
+    },
+    Autofill,
+    ApplyOutcome(Outcome),
+    Undo,
+    Redo,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Edge {
+    pub const ALL: [Edge; 4] = [Edge::Top, Edge::Bottom, Edge::Left, Edge::Right];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Edge::Top => "Top",
+            Edge::Bottom => "Bottom",
+            Edge::Left => "Left",
+            Edge::Right => "Right",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Arrow {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Action {
+    fn edit(&self, active_slot: Option<Slot>) -> Option<Edit> {
+        match self {
+            Action::EnterLetter(_) | Action::EnterRebus(_) | Action::Erase => {
+                active_slot.map(|slot| Edit::Typing {
+                    head: slot.head,
+                    is_across: slot.is_across,
+                })
+            }
+            Action::FillSlot(_) => active_slot.map(|_| Edit::Fill),
+            &Action::SetClue {
+                head, is_across, ..
+            } => Some(Edit::Clue { head, is_across }),
+            Action::RunGenerator { .. }
+            | Action::PickCandidate(_)
+            | Action::ApplyOutcome(Outcome::Blacks { .. }) => Some(Edit::Generate),
+            Action::ApplyOutcome(Outcome::Fill(_)) => Some(Edit::Fill),
+            Action::ApplyTemplate(_) => Some(Edit::Template),
+            Action::ToggleMark { .. } => active_slot.map(|_| Edit::Style),
+            Action::ToggleLock { .. } => active_slot.map(|_| Edit::Lock),
+            Action::ClearUnlocked => Some(Edit::Clear),
+            _ => None,
+        }
+    }
 }
 
 impl Store {
-    fn dispatch(&self) -> impl Fn(Action) {
-        |action| console_log("test")
+    fn new(cx: Scope, crossword: Crossword, dictionary: Dictionary) -> Self {
+        let generator = create_rw_signal(cx, GeneratorConfig::default());
+        let grid = crossword.grid;
+        let validity = create_memo(cx, move |_| {
+            let min_word_len = generator.with(|config| config.min_word_len);
+            grid.with(|grid| validity::check(grid, min_word_len))
+        });
+
+        Self {
+            crossword,
+            selection: Selection::new(cx),
+            dictionary,
+            status: create_rw_signal(cx, None),
+            last_seed: create_rw_signal(cx, None),
+            generator,
+            validity,
+            job: create_rw_signal(cx, None),
+            running: store_value(cx, None),
+            job_start: store_value(cx, None),
+            candidates: create_rw_signal(cx, Vec::new()),
+            rebus: create_rw_signal(cx, false),
+            shade: create_rw_signal(cx, DEFAULT_SHADE.to_owned()),
+            editing_bars: create_rw_signal(cx, false),
+        }
+    }
+
+    /// Runs `job` in the worker and applies its outcome when it finishes,
+    /// replacing any job already running. If the worker can't start or fails
+    /// along the way, this dispatches `fallback` to do the work here instead.
+    fn start_job(&self, cx: Scope, job: Job, fallback: Action) {
+        let this = *self;
+        let name = job.name();
+        let on_progress = move |report| {
+            if let Report::Progress {
+                iteration,
+                score,
+                best,
+            } = report
+            {
+                this.job.set(Some(JobProgress {
+                    name,
+                    iteration,
+                    score: Some(score),
+                    best,
+                }));
+            }
+        };
+
+        let run_here = move |e: String| {
+            console_warn(&format!("running {} here, the worker failed: {}", name, e));
+            this.stop_job();
+            this.status.set(Some(format!(
+                "The worker failed ({}), so this ran here instead",
+                e
+            )));
+            this.reduce(cx, fallback.clone());
+        };
+
+        match Running::start(&job, on_progress) {
+            Ok((running, outcome)) => {
+                self.running.set_value(Some(running));
+                self.job_start.set_value(Some(job.saved().clone()));
+                self.job.set(Some(JobProgress {
+                    name,
+                    iteration: 0,
+                    score: None,
+                    best: None,
+                }));
+                spawn_local(async move {
+                    // Cancelling drops the sender, so this only hears from jobs
+                    // that ran to the end or failed.
+                    match outcome.await {
+                        Ok(Ok(outcome)) => this.finish_job(cx, outcome),
+                        Ok(Err(e)) => run_here(e),
+                        Err(_) => {}
+                    }
+                });
+            }
+            Err(e) => run_here(format!("{:?}", e)),
+        }
+    }
+
+    fn stop_job(&self) {
+        self.running.set_value(None);
+        self.job_start.set_value(None);
+        self.job.set(None);
+    }
+
+    /// Stops the job and applies `outcome`, unless the grid has changed since
+    /// the job started, since the outcome was worked out for the old one.
+    fn finish_job(&self, cx: Scope, outcome: Outcome) {
+        let name = self
+            .job
+            .with_untracked(|job| job.as_ref().map(|job| job.name));
+        let start = self.job_start.get_value();
+        self.stop_job();
+
+        let now = self.crossword.to_saved();
+        if start.is_some_and(|start| !start.same_grid(&now)) {
+            self.status.set(Some(format!(
+                "The grid changed while {}, so the result was dropped",
+                name.unwrap_or("working").to_lowercase()
+            )));
+            return;
+        }
+        self.reduce(cx, Action::ApplyOutcome(outcome));
+    }
+
+    /// Stops the job and applies the best result it had reported.
+    fn take_best(&self, cx: Scope) {
+        let best = self
+            .job
+            .with_untracked(|job| job.as_ref().and_then(|job| job.best.clone()));
+        if let Some(best) = best {
+            self.finish_job(cx, best);
+        }
+    }
+
+    fn apply(&self, cx: Scope, outcome: Outcome) {
+        let crossword = self.crossword;
+        match outcome {
+            Outcome::Blacks { candidates, seed } => {
+                if let Some(best) = candidates.first() {
+                    crossword.set_blacks(cx, &best.cells);
+                }
+                self.candidates.set(candidates);
+                self.last_seed.set(Some(seed));
+            }
+            Outcome::Fill(filled) => {
+                let saved = crossword.to_saved();
+                for i in 0..saved.cells.len() {
+                    let cell = (i % saved.width, i / saved.width);
+                    if let (Some(before), Some(after)) = (saved.letters(cell), filled.letters(cell))
+                    {
+                        // A lock keeps the letters in a cell, so an empty
+                        // locked cell still takes the fill.
+                        if before != after && (before == " " || !saved.is_locked(cell)) {
+                            crossword.set_cell()(cell, after);
+                        }
+                    }
+                }
+                self.status.set(None);
+            }
+            Outcome::Unfillable { message, slot } => {
+                if let Some((head, is_across)) = slot {
+                    self.selection
+                        .active_slot
+                        .set(crossword.get_slot()(head, is_across));
+                }
+                self.status.set(Some(message));
+            }
+        }
+    }
+
+    fn dispatch(&self, cx: Scope) -> impl Fn(Action) + Copy {
+        let this = *self;
+        move |action| this.reduce(cx, action)
+    }
+
+    fn reduce(&self, cx: Scope, action: Action) {
+        let crossword = self.crossword;
+        let selection = self.selection;
+        let active_slot = selection.active_slot;
+        let caret_cell = selection.caret_cell();
+        let click_cell = selection.click_cell(crossword.get_slot(), crossword.cell_exists());
+        let is_locked = crossword.is_locked();
+        let letter_at = crossword.letter_at();
+
+        if let Some(edit) = action.edit(active_slot.get_untracked()) {
+            crossword.record(edit, active_slot.get_untracked());
+        }
+
+        match action {
+            Action::ClickCell(cell) => click_cell(cell),
+            Action::EnterLetter(letter) => {
+                if let Some(cell) = caret_cell() {
+                    crossword.set_cell()(cell, letter.to_string());
+                    selection.advance_caret()();
+                }
+            }
+            Action::OpenRebus => {
+                if caret_cell().is_some() {
+                    self.rebus.set(true);
+                }
+            }
+            Action::EnterRebus(letters) => {
+                self.rebus.set(false);
+                let letters = letters
+                    .chars()
+                    .filter(|c| c.is_alphanumeric())
+                    .collect::<String>();
+                if let Some(cell) = caret_cell() {
+                    let letters = if letters.is_empty() {
+                        " ".to_owned()
+                    } else {
+                        letters
+                    };
+                    crossword.set_cell()(cell, letters);
+                    selection.advance_caret()();
+                }
+            }
+            Action::CloseRebus => self.rebus.set(false),
+            Action::FillSlot(word) => {
+                if let Some(slot) = active_slot.get_untracked() {
+                    let word = word.chars().collect::<Vec<_>>();
+                    let spans = crossword.slot_spans()(slot);
+                    for (cell, span) in slot.cells().zip(spans) {
+                        let is_open = !is_locked(cell) || letter_at(cell).is_none();
+                        if let Some(letters) = word.get(span).filter(|_| is_open) {
+                            crossword.set_cell()(cell, letters.iter().collect());
+                        }
+                    }
+                }
+            }
+            Action::Erase => {
+                if active_slot.get_untracked().is_some() {
+                    if caret_cell().and_then(crossword.letter_at()).is_none() {
+                        selection.retreat_caret()();
+                    }
+                    if let Some(cell) = caret_cell() {
+                        crossword.set_cell()(cell, " ".to_owned());
+                    }
+                }
+            }
+            Action::ToggleBlack => {
+                // Recorded here so that trying to black out a locked cell
+                // doesn't leave an empty undo step.
+                if let Some(cell) = caret_cell().filter(|&cell| !is_locked(cell)) {
+                    crossword.record(Edit::Black, active_slot.get_untracked());
+                    crossword.toggle_cell(cx)(cell);
+                    active_slot.set(None);
+                }
+            }
+            Action::ToggleBar { cell, edge } => {
+                // Recorded here so that the outer edge of the grid, which
+                // can't hold a bar, doesn't leave an empty undo step.
+                if crossword.can_bar()(cell, edge) {
+                    crossword.record(Edit::Bar, active_slot.get_untracked());
+                    crossword.grid.update(|grid| grid.toggle_bar(cell, edge));
+                    active_slot.set(None);
+                }
+            }
+            Action::MoveCaret(arrow) => {
+                if let Some((x, y)) = caret_cell() {
+                    let new_cell = match arrow {
+                        Arrow::Left => x.checked_sub(1).map(|x| (x, y)),
+                        Arrow::Right => Some((x + 1, y)),
+                        Arrow::Up => y.checked_sub(1).map(|y| (x, y)),
+                        Arrow::Down => Some((x, y + 1)),
+                    };
+                    if let Some(new_cell) = new_cell {
+                        click_cell(new_cell);
+                    }
+                }
+            }
+            Action::SwitchDirection => {
+                if let Some(cell) = caret_cell() {
+                    click_cell(cell);
+                }
+            }
+            Action::NextWord => selection.next_word(crossword.answers)(),
+            Action::ClearSelection => selection.remove_selection()(),
+            Action::SelectWord { head, is_across } => {
+                if let Some(slot) = crossword.get_slot()(head, is_across) {
+                    active_slot.set(Some(slot));
+                }
+            }
+            Action::SetClue {
+                head,
+                is_across,
+                clue,
+            } => crossword.set_clue()(head, is_across, clue),
+            Action::SetSymmetry(symmetry) => crossword.symmetry.set(symmetry),
+            Action::SetGeneratorConfig(config) => {
+                self.generator.set(config);
+            }
+            Action::ChoosePreset(preset) => {
+                let saved = crossword.to_saved();
+                let (width, height) = preset.size();
+                // Recorded here so that a preset of the same size doesn't leave
+                // an empty undo step.
+                if saved.size() != (width, height) {
+                    crossword.record(Edit::Resize, active_slot.get_untracked());
+                    active_slot.set(None);
+                    crossword.restore(cx, &saved.resized_to(width, height));
+                }
+                self.generator.set(preset.config());
+            }
+            Action::RunGenerator { seed } => {
+                let saved = crossword.to_saved();
+                let outcome =
+                    worker::generate(&saved, self.generator.get_untracked(), seed, |_| ());
+                self.apply(cx, outcome);
+            }
+            Action::PickCandidate(i) => {
+                if let Some(candidate) = self.candidates.with_untracked(|c| c.get(i).cloned()) {
+                    crossword.set_blacks(cx, &candidate.cells);
+                }
+            }
+            Action::ApplyTemplate(template) => {
+                let saved = template.apply(&crossword.to_saved());
+                crossword.restore(cx, &saved);
+            }
+            Action::ToggleMark { mark, whole_slot } => {
+                let cells = match (active_slot.get_untracked(), caret_cell()) {
+                    (Some(slot), _) if whole_slot => slot.cells().collect(),
+                    (_, Some(cell)) => vec![cell],
+                    _ => Vec::new(),
+                };
+                crossword.toggle_mark(&cells, &mark);
+            }
+            Action::ToggleLock { whole_slot } => {
+                let cells = match (active_slot.get_untracked(), caret_cell()) {
+                    (Some(slot), _) if whole_slot => slot.cells().collect(),
+                    (_, Some(cell)) => vec![cell],
+                    _ => Vec::new(),
+                };
+                crossword.toggle_lock(&cells);
+            }
+            Action::ClearUnlocked => crossword.clear_unlocked(),
+            Action::Resize { edge, grow } => {
+                let saved = crossword.to_saved().resized(edge, grow);
+                let (width, height) = saved.size();
+                // Recorded here so that a resize past the limits doesn't leave
+                // an empty undo step.
+                if (MIN_SIZE..=MAX_SIZE).contains(&width) && (MIN_SIZE..=MAX_SIZE).contains(&height)
+                {
+                    crossword.record(Edit::Resize, active_slot.get_untracked());
+                    active_slot.set(None);
+                    crossword.restore(cx, &saved);
+                }
+            }
+            Action::Autofill => {
+                let saved = crossword.to_saved();
+                let outcome = self
+                    .dictionary
+                    .list
+                    .with(|list| worker::fill(&saved, list, |_| ()));
+                // Only recorded once there's a fill, so a failed attempt doesn't
+                // leave an empty undo step.
+                if let Outcome::Fill(_) = outcome {
+                    crossword.record(Edit::Fill, active_slot.get_untracked());
+                }
+                self.apply(cx, outcome);
+            }
+            Action::ApplyOutcome(outcome) => self.apply(cx, outcome),
+            Action::Undo => crossword.undo(cx)(active_slot),
+            Action::Redo => crossword.redo(cx)(active_slot),
+        }
     }
 }
 
@@ -40,10 +559,13 @@ struct Crossword {
     grid: RwSignal<Grid>,
     answers: Memo<Answers>,
     answer_map: Memo<AnswerMap>,
+    history: RwSignal<History>,
+    symmetry: RwSignal<Symmetry>,
 }
 
 struct Theme {
     rosewater: &'static str,
+    red: &'static str,
     lavender: &'static str,
     text: &'static str,
     subtext1: &'static str,
@@ -62,8 +584,8 @@ struct Theme {
 impl Theme {
     fn to_css(&self) -> String {
         format!(
-"--cursor:{};--text:{};--crust:{};--surface0:{};--surface1:{};--surface2:{};--overlay0:{};--overlay1:{};--overlay2:{};--subtext0:{};--subtext1:{};--base:{};--crust:{};--mantle:{};",
-            self.lavender, self.text, self.crust, self.surface0, self.surface1, self.surface2, self.overlay0, self.overlay1, self.overlay2, self.subtext0, self.subtext1, self.base, self.crust, self.mantle
+"--cursor:{};--red:{};--text:{};--crust:{};--surface0:{};--surface1:{};--surface2:{};--overlay0:{};--overlay1:{};--overlay2:{};--subtext0:{};--subtext1:{};--base:{};--crust:{};--mantle:{};",
+            self.lavender, self.red, self.text, self.crust, self.surface0, self.surface1, self.surface2, self.overlay0, self.overlay1, self.overlay2, self.subtext0, self.subtext1, self.base, self.crust, self.mantle
         )
     }
 }
@@ -72,6 +594,7 @@ impl Theme {
     fn catpuccin_mocha() -> Self {
         Self {
             rosewater: "#f5e0dc",
+            red: "#f38ba8",
             lavender: "#b4befe",
             text: "#cdd6f4",
             subtext1: "#bac2de",
@@ -91,6 +614,7 @@ impl Theme {
     fn catpuccin_latte() -> Self {
         Self {
             rosewater: "#dc8a78",
+            red: "#d20f39",
             lavender: "#7287fd",
             text: "#4c4f69",
             subtext1: "#5c5f77",
@@ -110,6 +634,7 @@ impl Theme {
     fn catpuccin_frappe() -> Self {
         Self {
             rosewater: "#f2d5cf",
+            red: "#e78284",
             lavender: "#babbf1",
             text: "#c6d0f5",
             subtext1: "#b5bfe2",
@@ -134,11 +659,28 @@ impl Default for Theme {
 }
 
 impl Crossword {
-    fn new(cx: Scope) -> Self {
+    fn open(cx: Scope, library: Library, id: Uuid) -> Self {
         console_log("creating crossword");
-        let grid = create_rw_signal(cx, Grid::new(cx));
+        let crossword = match storage::load(id) {
+            Some(saved) => Self::from_saved(cx, &saved),
+            None => Self::from_grid(cx, Grid::new(cx, DEFAULT_WIDTH, DEFAULT_HEIGHT)),
+        };
+
+        create_effect(cx, move |first_run: Option<()>| {
+            let saved = crossword.to_saved();
+            if first_run.is_some() {
+                storage::save(id, &saved);
+                library.touch(id, &saved);
+            }
+        });
+
+        crossword
+    }
+
+    fn from_grid(cx: Scope, grid: Grid) -> Self {
+        let grid = create_rw_signal(cx, grid);
         let answers = create_memo(cx, move |previous_answers| {
-            grid.with(|g| Answers::new(previous_answers, g))
+            grid.with(|g| Answers::new(cx, previous_answers, g))
         });
         let answer_map = create_memo(cx, move |_| answers.with(|a| a.answer_map()));
 
@@ -146,14 +688,194 @@ impl Crossword {
             grid,
             answers,
             answer_map,
+            history: create_rw_signal(cx, History::default()),
+            symmetry: create_rw_signal(cx, Symmetry::default()),
+        }
+    }
+
+    /// Snapshots the puzzle before `edit` is applied so it can be undone.
+    fn record(&self, edit: Edit, selection: Option<Slot>) {
+        if self.history.with_untracked(|h| h.needs_step(edit)) {
+            let step = Step {
+                saved: self.to_saved(),
+                selection,
+            };
+            self.history.update(|h| h.push(edit, step));
+        }
+    }
+
+    fn undo(&self, cx: Scope) -> impl Fn(RwSignal<Option<Slot>>) + Copy {
+        let this = *self;
+        move |active_slot| {
+            let current = Step {
+                saved: this.to_saved(),
+                selection: active_slot.get_untracked(),
+            };
+            if let Some(step) = this.history.try_update(|h| h.undo(current)).flatten() {
+                this.restore(cx, &step.saved);
+                active_slot.set(step.selection);
+            }
+        }
+    }
+
+    fn redo(&self, cx: Scope) -> impl Fn(RwSignal<Option<Slot>>) + Copy {
+        let this = *self;
+        move |active_slot| {
+            let current = Step {
+                saved: this.to_saved(),
+                selection: active_slot.get_untracked(),
+            };
+            if let Some(step) = this.history.try_update(|h| h.redo(current)).flatten() {
+                this.restore(cx, &step.saved);
+                active_slot.set(step.selection);
+            }
+        }
+    }
+
+    fn set_clue(&self) -> impl Fn((usize, usize), bool, String) + Copy {
+        let this = *self;
+        move |head, is_across, clue| {
+            let Some(word_clue) = this.answers.with(|answers| {
+                answers
+                    .get(head)
+                    .and_then(|h| h.word(is_across).as_ref())
+                    .map(|w| w.clue)
+            }) else {
+                return;
+            };
+
+            word_clue.set(clue);
+        }
+    }
+
+    fn clue_rows(&self) -> impl Fn(bool) -> Vec<ClueRow> + Copy {
+        let answers = self.answers;
+        move |is_across| {
+            answers.with(|answers| {
+                answers
+                    .answers
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, head)| {
+                        head.word(is_across).as_ref().map(|word| ClueRow {
+                            number: i + 1,
+                            head: head.head,
+                            is_across,
+                            answer: word.answer.clone(),
+                            locked: word.locked.clone(),
+                            clue: word.clue,
+                        })
+                    })
+                    .collect()
+            })
+        }
+    }
+
+    /// The slot's current fill, with a space for each empty cell.
+    fn slot_pattern(&self) -> impl Fn(Slot) -> String + Copy {
+        let letter_at = self.letter_at();
+        move |slot| {
+            slot.cells()
+                .map(|cell| letter_at(cell).map_or_else(|| " ".to_owned(), |l| l.to_uppercase()))
+                .collect()
+        }
+    }
+
+    /// Where each of the slot's cells falls in `slot_pattern`, which is further
+    /// along than the cell's position after a rebus.
+    fn slot_spans(&self) -> impl Fn(Slot) -> Vec<Range<usize>> + Copy {
+        let letter_at = self.letter_at();
+        move |slot| {
+            slot.cells()
+                .scan(0, |start, cell| {
+                    let span = *start..*start + letter_at(cell).map_or(1, |l| l.chars().count());
+                    *start = span.end;
+                    Some(span)
+                })
+                .collect()
         }
     }
 
+    /// The slots crossing `slot` at its empty cells, which are the ones a new
+    /// fill could block.
+    fn crossings(&self) -> impl Fn(Slot) -> Vec<Crossing> + Copy {
+        let letter_at = self.letter_at();
+        let get_slot = self.get_slot();
+        let slot_pattern = self.slot_pattern();
+        let slot_spans = self.slot_spans();
+        move |slot| {
+            slot.cells()
+                .zip(slot_spans(slot))
+                .filter(|&(cell, _)| letter_at(cell).is_none())
+                .filter_map(|(cell, span)| {
+                    get_slot(cell, !slot.is_across).map(|crossing| Crossing {
+                        position: span.start,
+                        offset: slot_spans(crossing)[crossing.caret_position].start,
+                        pattern: slot_pattern(crossing),
+                    })
+                })
+                .collect()
+        }
+    }
+
+    /// Toggles `cell` along with its symmetric partners, so they all end up
+    /// matching it.
     fn toggle_cell(&self, cx: Scope) -> impl Fn((usize, usize)) + Copy {
         let grid = self.grid;
+        let symmetry = self.symmetry;
         move |cell| {
             grid.update(|grid| {
-                grid.toggle_cell(cell, cx);
+                let Some(&current) = grid.get(cell) else {
+                    return;
+                };
+                let black = current.is_some();
+                for partner in grid.orbit(symmetry.get_untracked(), cell) {
+                    grid.black(partner, cx, black);
+                }
+            })
+        }
+    }
+
+    /// The white cells whose symmetric partners are black.
+    fn symmetry_violations(&self) -> impl Fn() -> Vec<(usize, usize)> + Copy {
+        let grid = self.grid;
+        let symmetry = self.symmetry;
+        move || {
+            let symmetry = symmetry();
+            grid.with(|grid| {
+                (0..grid.cells.len())
+                    .map(|i| (i % grid.width, i / grid.width))
+                    .filter(|&cell| {
+                        grid.at(cell) && grid.orbit(symmetry, cell).any(|partner| !grid.at(partner))
+                    })
+                    .collect()
+            })
+        }
+    }
+
+    /// Makes the cells black where `cells` is true and white elsewhere, unless
+    /// the grid has changed size since `cells` was generated.
+    fn set_blacks(&self, cx: Scope, cells: &[bool]) {
+        self.grid.update(|grid| {
+            if grid.cells.len() != cells.len() {
+                return;
+            }
+            for (i, &is_black) in cells.iter().enumerate() {
+                let coord = (i % grid.width, i / grid.width);
+                grid.black(coord, cx, is_black);
+            }
+        });
+    }
+
+    /// The letters in `cell`, which are several for a rebus, or `None` when
+    /// it's empty or black.
+    fn letter_at(&self) -> impl Fn((usize, usize)) -> Option<String> + Copy {
+        let grid = self.grid;
+        move |cell| {
+            grid.with(|grid| {
+                grid.get(cell)
+                    .and_then(|&cell| cell.map(|c| c.letter.get()))
+                    .filter(|l| l != " ")
             })
         }
     }
@@ -176,6 +898,58 @@ impl Crossword {
         }
     }
 
+    /// Marks all of `cells`, or clears the mark if they all have it.
+    fn toggle_mark(&self, cells: &[(usize, usize)], mark: &Mark) {
+        let styles = self.grid.with_untracked(|grid| {
+            cells
+                .iter()
+                .filter_map(|&cell| grid.get(cell).and_then(|&c| c).map(|c| c.style))
+                .collect::<Vec<_>>()
+        });
+        let on = !styles
+            .iter()
+            .all(|style| style.with_untracked(|s| s.has(mark)));
+        for style in styles {
+            style.update(|style| style.set(mark, on));
+        }
+    }
+
+    /// Locks all of `cells`, or unlocks them if they're all locked.
+    fn toggle_lock(&self, cells: &[(usize, usize)]) {
+        let locks = self.grid.with_untracked(|grid| {
+            cells
+                .iter()
+                .filter_map(|&cell| grid.get(cell).and_then(|&c| c).map(|c| c.locked))
+                .collect::<Vec<_>>()
+        });
+        let on = !locks.iter().all(|locked| locked.get_untracked());
+        for locked in locks {
+            locked.set(on);
+        }
+    }
+
+    /// Empties every white cell that isn't locked.
+    fn clear_unlocked(&self) {
+        self.grid.with_untracked(|grid| {
+            for cell in grid.cells.iter().flatten() {
+                if !cell.locked.get_untracked() && cell.letter.with_untracked(|l| l != " ") {
+                    cell.letter.set(" ".to_owned());
+                }
+            }
+        });
+    }
+
+    fn is_locked(&self) -> impl Fn((usize, usize)) -> bool + Copy {
+        let grid = self.grid;
+        move |cell| {
+            grid.with_untracked(|grid| {
+                grid.get(cell)
+                    .and_then(|&c| c)
+                    .is_some_and(|c| c.locked.get_untracked())
+            })
+        }
+    }
+
     fn cell_exists(&self) -> impl Fn((usize, usize)) -> bool + Copy {
         let grid = self.grid;
 
@@ -206,7 +980,7 @@ impl Crossword {
         }
     }
 
-    fn set_cell(&self) -> impl Fn((usize, usize), char) + Copy {
+    fn set_cell(&self) -> impl Fn((usize, usize), String) + Copy {
         let grid = self.grid;
 
         move |cell, letter| grid.with(|g| g.set_cell(cell, letter))
@@ -216,11 +990,15 @@ impl Crossword {
         let grid = self.grid;
         move |cell| {
             grid.with(|grid| {
-                let at = |c| grid.get(c).map_or(true, |c| c.is_none());
-                let top = cell.1 == 0 || at((cell.0, cell.1 - 1));
-                let left = cell.0 == 0 || at((cell.0 - 1, cell.1));
-                let right = at((cell.0 + 1, cell.1));
-                let bottom = at((cell.0, cell.1 + 1));
+                let at = |c| grid.get(c).is_none_or(|c| c.is_none());
+                let top = cell.1 == 0
+                    || at((cell.0, cell.1 - 1))
+                    || grid.barred((cell.0, cell.1 - 1), false);
+                let left = cell.0 == 0
+                    || at((cell.0 - 1, cell.1))
+                    || grid.barred((cell.0 - 1, cell.1), true);
+                let right = at((cell.0 + 1, cell.1)) || grid.barred(cell, true);
+                let bottom = at((cell.0, cell.1 + 1)) || grid.barred(cell, false);
                 let top_left = cell.1 == 0 || cell.0 == 0 || at((cell.0 - 1, cell.1 - 1));
                 let top_right = cell.1 == 0 || at((cell.0 + 1, cell.1 - 1));
                 let bottom_left = cell.0 == 0 || at((cell.0 - 1, cell.1 + 1));
@@ -236,6 +1014,18 @@ impl Crossword {
         }
     }
 
+    fn bars_at(&self) -> impl Fn((usize, usize)) -> Bars + Copy {
+        let grid = self.grid;
+        move |cell| grid.with(|grid| grid.bars_at(cell))
+    }
+
+    /// Whether `edge` of `cell` is between two cells, so it can hold a bar,
+    /// rather than on the outside of the grid.
+    fn can_bar(&self) -> impl Fn((usize, usize), Edge) -> bool + Copy {
+        let grid = self.grid;
+        move |cell, edge| grid.with_untracked(|grid| grid.bar_owner(cell, edge).is_some())
+    }
+
     fn answer_id_at(&self) -> impl Fn((usize, usize)) -> Option<usize> + Copy {
         let answers = self.answers;
         move |cell| answers.with(|answers| answers.answer_keys.get(&cell).copied().map(|id| id + 1))
@@ -257,39 +1047,173 @@ impl Crossword {
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct Cell {
     id: Uuid,
-    letter: RwSignal<char>,
+    /// A space when empty, and more than one letter for a rebus.
+    letter: RwSignal<String>,
+    style: RwSignal<CellStyle>,
+    /// Whether fill tools leave the letter alone and the cell stays white.
+    locked: RwSignal<bool>,
 }
 
 impl Cell {
     fn new(cx: Scope) -> Self {
+        Self::with_contents(cx, " ".to_owned(), CellStyle::default(), false)
+    }
+
+    fn with_contents(cx: Scope, letter: String, style: CellStyle, locked: bool) -> Self {
         Self {
             id: Uuid::new_v4(),
-            letter: create_rw_signal(cx, ' '),
+            letter: create_rw_signal(cx, letter),
+            style: create_rw_signal(cx, style),
+            locked: create_rw_signal(cx, locked),
+        }
+    }
+}
+
+/// How a cell is marked out, which theme puzzles use to highlight hidden
+/// words.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, Default)]
+pub struct CellStyle {
+    #[serde(default)]
+    pub circle: bool,
+    /// The colour the cell is shaded with, as CSS hex.
+    #[serde(default)]
+    pub shade: Option<String>,
+    #[serde(default)]
+    pub bold: bool,
+}
+
+impl CellStyle {
+    pub fn is_plain(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn has(&self, mark: &Mark) -> bool {
+        match mark {
+            Mark::Circle => self.circle,
+            Mark::Bold => self.bold,
+            Mark::Shade(colour) => self.shade.as_ref() == Some(colour),
+        }
+    }
+
+    fn set(&mut self, mark: &Mark, on: bool) {
+        match mark {
+            Mark::Circle => self.circle = on,
+            Mark::Bold => self.bold = on,
+            Mark::Shade(colour) => self.shade = on.then(|| colour.clone()),
+        }
+    }
+}
+
+/// The bars on a cell's right and bottom edges, which end words in barred
+/// grids the way black squares do.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Bars {
+    pub right: bool,
+    pub bottom: bool,
+}
+
+impl Bars {
+    /// The cell whose bars hold the bar on `edge` of `cell`, and whether it's
+    /// that cell's right bar. `None` on the outside of a grid of `size`.
+    pub fn owner(
+        (x, y): (usize, usize),
+        edge: Edge,
+        (width, height): (usize, usize),
+    ) -> Option<((usize, usize), bool)> {
+        let (owner, right) = match edge {
+            Edge::Left => ((x.checked_sub(1)?, y), true),
+            Edge::Right => ((x, y), true),
+            Edge::Top => ((x, y.checked_sub(1)?), false),
+            Edge::Bottom => ((x, y), false),
+        };
+        let (x, y) = owner;
+        let inside = if right {
+            x + 1 < width && y < height
+        } else {
+            x < width && y + 1 < height
+        };
+        inside.then_some((owner, right))
+    }
+
+    pub fn is_plain(&self) -> bool {
+        !self.right && !self.bottom
+    }
+
+    fn get(&self, right: bool) -> bool {
+        if right {
+            self.right
+        } else {
+            self.bottom
+        }
+    }
+
+    fn get_mut(&mut self, right: bool) -> &mut bool {
+        if right {
+            &mut self.right
+        } else {
+            &mut self.bottom
         }
     }
 }
 
+/// One part of a `CellStyle` that can be toggled on the selected cells.
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Mark {
+    Circle,
+    Bold,
+    Shade(String),
+}
+
 #[derive(PartialEq, Debug)]
 struct Grid {
     cells: Vec<Option<Cell>>,
     width: usize,
+    /// The bars of every cell, black or white, in the same order as `cells`.
+    bars: Vec<Bars>,
 }
 
 impl Grid {
-    fn new(cx: Scope) -> Self {
-        let cells = (0..DEFAULT_WIDTH * DEFAULT_HEIGHT)
+    fn new(cx: Scope, width: usize, height: usize) -> Self {
+        let cells = (0..width * height)
             .map(|i| Some(Cell::new(cx)))
             .collect::<Vec<_>>();
 
         Self {
+            bars: vec![Bars::default(); cells.len()],
             cells,
-            width: DEFAULT_WIDTH,
+            width,
         }
     }
 
+    /// The cell that keeps the bar on `edge` of `cell`, and whether it's that
+    /// cell's right edge.
+    fn bar_owner(&self, cell: (usize, usize), edge: Edge) -> Option<((usize, usize), bool)> {
+        Bars::owner(cell, edge, (self.width, self.cells.len() / self.width))
+    }
+
+    /// Adds the bar on `edge` of `cell`, or removes it if it's there.
+    fn toggle_bar(&mut self, cell: (usize, usize), edge: Edge) {
+        if let Some(((x, y), right)) = self.bar_owner(cell, edge) {
+            let bar = self.bars[x + y * self.width].get_mut(right);
+            *bar = !*bar;
+        }
+    }
+
+    fn bars_at(&self, (x, y): (usize, usize)) -> Bars {
+        if x >= self.width {
+            return Bars::default();
+        }
+        self.bars
+            .get(x + y * self.width)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Makes `cell` black or white, except that locked cells stay white.
     fn black(&mut self, cell: (usize, usize), cx: Scope, black: bool) {
         if let Some(l) = self.get_mut(cell) {
             *l = match (*l, black) {
+                (Some(c), true) if c.locked.get_untracked() => Some(c),
                 (_, true) => None,
                 (None, false) => Some(Cell::new(cx)),
                 (Some(c), false) => Some(c),
@@ -297,17 +1221,22 @@ impl Grid {
         }
     }
 
-    fn toggle_cell(&mut self, cell: (usize, usize), cx: Scope) {
-        if let Some(l) = self.get_mut(cell) {
-            *l = match l {
-                Some(_) => None,
-                None => Some(Cell::new(cx)),
-            };
-        }
+    fn orbit(
+        &self,
+        symmetry: Symmetry,
+        (x, y): (usize, usize),
+    ) -> impl Iterator<Item = (usize, usize)> {
+        symmetry
+            .orbit(
+                (x as isize, y as isize),
+                self.width,
+                self.cells.len() / self.width,
+            )
+            .map(|(x, y)| (x as usize, y as usize))
     }
 
-    fn set_cell(&self, cell: (usize, usize), letter: char) {
-        if let Some(cell) = self.char_at(cell) {
+    fn set_cell(&self, cell: (usize, usize), letter: String) {
+        if let Some(cell) = self.letters_at(cell) {
             cell.set(letter);
         }
     }
@@ -326,12 +1255,12 @@ impl Grid {
             .and_then(|row| row.get_mut(cell.0))
     }
 
-    fn char_at(&self, cell: (usize, usize)) -> Option<RwSignal<char>> {
+    fn letters_at(&self, cell: (usize, usize)) -> Option<RwSignal<String>> {
         self.get(cell).and_then(|&c| c).map(|c| c.letter)
     }
 }
 
-impl<'a> BoolMatrix for &'a Grid {
+impl BoolMatrix for &Grid {
     fn rows(self) -> usize {
         self.cells.len() / self.width
     }
@@ -341,14 +1270,18 @@ impl<'a> BoolMatrix for &'a Grid {
     }
 
     fn at(self, cell: (usize, usize)) -> bool {
-        self.get(cell).map_or(false, |c| c.is_some())
+        self.get(cell).is_some_and(|c| c.is_some())
+    }
+
+    fn barred(self, cell: (usize, usize), is_across: bool) -> bool {
+        self.bars_at(cell).get(is_across)
     }
 }
 
-impl<'a> TriBoolMatrix for &'a Grid {
+impl TriBoolMatrix for &Grid {
     fn maybe_at(self, cell: (usize, usize)) -> Option<bool> {
         self.get(cell)
-            .and_then(|c| c.map(|c| c.letter.get() == ' '))
+            .and_then(|c| c.map(|c| c.letter.with(|l| l == " ") && !c.locked.get_untracked()))
     }
 }
 
@@ -366,6 +1299,21 @@ pub struct Slot {
     caret_position: usize,
 }
 
+impl Slot {
+    fn cells(&self) -> impl Iterator<Item = (usize, usize)> {
+        let Slot {
+            head, is_across, ..
+        } = *self;
+        (0..self.len).map(move |i| {
+            if is_across {
+                (head.0 + i, head.1)
+            } else {
+                (head.0, head.1 + i)
+            }
+        })
+    }
+}
+
 impl Selection {
     fn new(cx: Scope) -> Self {
         let active_slot = create_rw_signal(cx, None);
@@ -407,7 +1355,7 @@ impl Selection {
     fn is_across(&self) -> impl Fn() -> bool + Copy {
         let active_slot = self.active_slot;
 
-        move || active_slot().map_or(false, |s| s.is_across)
+        move || active_slot().is_some_and(|s| s.is_across)
     }
 
     fn remove_selection(&self) -> impl Fn() + Copy {
@@ -421,7 +1369,7 @@ impl Selection {
 
     fn hide_caret(&self) -> impl Fn() -> bool + Copy {
         let active_slot = self.active_slot;
-        move || active_slot().map_or(true, |a| a.caret_position >= a.len)
+        move || active_slot().is_none_or(|a| a.caret_position >= a.len)
     }
 
     fn click_cell<S, C>(&self, get_slot: S, cell_exists: C) -> impl Fn((usize, usize)) + Copy
@@ -533,153 +1481,548 @@ fn log(is_across: impl Fn() -> bool + Copy + 'static) {
 pub fn App(cx: Scope) -> impl IntoView {
     // set_interval_with_handle(|| console_log("test"), duration);
 
-    let crossword = Crossword::new(cx);
-    provide_context(cx, crossword);
-    let selection = Selection::new(cx);
-    provide_context(cx, selection);
+    let library = Library::new(cx);
+    provide_context(cx, library);
+    provide_context(cx, Dictionary::new(cx));
+    provide_context(cx, Templates::new(cx));
 
-    let grid = crossword.grid;
-    let answers = crossword.answers;
+    let editor = move || library.open.get().map(|id| view! { cx, <Editor id=id/> });
 
-    let set_cell = crossword.set_cell();
-    let toggle_cell = crossword.toggle_cell(cx);
-    let next_word = selection.next_word(answers);
+    view! { cx,
+        <div class="app">
+            <div class="content">
+                <Header/>
+                {editor}
+            </div>
+            <PuzzleList/>
+            <Dialog/>
+        </div>
+    }
+}
 
-    let letter_at = move |cell: (usize, usize)| {
-        grid.with(|grid| {
-            grid.get(cell)
-                .and_then(|&cell| cell.map(|c| c.letter.get()))
-                .filter(|&l| l != ' ')
-        })
-    };
+#[component]
+pub fn Editor(cx: Scope, id: Uuid) -> impl IntoView {
+    let library = use_context::<Library>(cx).expect("Parent did not provide library");
+    let dictionary = use_context::<Dictionary>(cx).expect("Parent did not provide dictionary");
+    let store = Store::new(cx, Crossword::open(cx, library, id), dictionary);
+    if let Some(config) = storage::load_generator_config() {
+        store.generator.set(config);
+    }
+    create_effect(cx, move |_| {
+        store.generator.with(storage::save_generator_config)
+    });
+    provide_context(cx, store);
+    provide_context(cx, store.crossword);
+    provide_context(cx, store.selection);
+
+    let crossword = store.crossword;
+    let dispatch = store.dispatch(cx);
 
-    let active_slot = selection.active_slot;
-    let caret_position = selection.caret_position(cx);
-    let caret_cell = selection.caret_cell();
-    let remove_selection = selection.remove_selection();
     let remove_selection = move |ev: FocusEvent| {
         let target = ev.current_target().unwrap().dyn_into::<Element>().unwrap();
         if let Some(focus_target) = ev.related_target() {
             let focus_target = focus_target.dyn_into::<Element>().unwrap();
             if !target.contains(Some(&focus_target)) {
-                remove_selection();
+                dispatch(Action::ClearSelection);
             }
         } else {
-            remove_selection();
+            dispatch(Action::ClearSelection);
         }
     };
-    let advance_caret = selection.advance_caret();
-    let retreat_caret = selection.retreat_caret();
-    let get_slot = crossword.get_slot();
-    let cell_exists = crossword.cell_exists();
-    let click_cell = selection.click_cell(get_slot, cell_exists);
-
-    let press_keydown = move |ev: Event| {
-        let ev = ev.dyn_into::<KeyboardEvent>().unwrap();
-
-        match ev.key().as_str() {
-            l if l.len() == 1 && l.is_ascii() => {
-                ev.prevent_default();
-                if let Some(selected_cell) = caret_cell() {
-                    let letter = l.chars().next().unwrap();
-                    set_cell(selected_cell, letter);
-                    advance_caret();
+
+    let press_keydown = move |ev: KeyboardEvent| {
+        let is_typing_in_field = ev
+            .target()
+            .and_then(|t| t.dyn_into::<Element>().ok())
+            .is_some_and(|e| matches!(e.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT"));
+        if is_typing_in_field {
+            return;
+        }
+
+        let action = match ev.key().as_str() {
+            "z" | "Z" if ev.ctrl_key() || ev.meta_key() => {
+                if ev.shift_key() {
+                    Action::Redo
+                } else {
+                    Action::Undo
                 }
             }
-            "Backspace" => {
-                ev.prevent_default();
-                if active_slot().is_some() {
-                    if caret_cell().and_then(letter_at).is_none() {
-                        retreat_caret();
-                    }
-                    if let Some(cell) = caret_cell() {
-                        set_cell(cell, ' ');
+            "y" if ev.ctrl_key() || ev.meta_key() => Action::Redo,
+            "Insert" | "Escape" => Action::OpenRebus,
+            // Matched by code, since Alt changes the key on some layouts.
+            _ if ev.alt_key() => {
+                let whole_slot = ev.shift_key();
+                let mark = match ev.code().as_str() {
+                    "KeyC" => Mark::Circle,
+                    "KeyS" => Mark::Shade(store.shade.get_untracked()),
+                    "KeyB" => Mark::Bold,
+                    "KeyL" => {
+                        ev.prevent_default();
+                        return dispatch(Action::ToggleLock { whole_slot });
                     }
-                }
-            }
-            "Delete" => {
-                ev.prevent_default();
-                if let Some(selected_cell) = caret_cell() {
-                    toggle_cell(selected_cell);
-                    active_slot.update(|existing| *existing = None);
-                }
+                    _ => return,
+                };
+                Action::ToggleMark { mark, whole_slot }
             }
-            "Tab" => {
-                ev.prevent_default();
-                next_word();
+            // Letters and digits, or a space to clear the cell. Punctuation
+            // would end up in exported solutions.
+            l if l.len() == 1 && l.chars().all(|c| c == ' ' || c.is_ascii_alphanumeric()) => {
+                Action::EnterLetter(l.chars().next().unwrap())
             }
-            "ArrowRight" => {
-                ev.prevent_default();
-                if let Some(selected_cell) = caret_cell() {
-                    let new_cell = (selected_cell.0 + 1, selected_cell.1);
-                    click_cell(new_cell);
-                }
+            "Backspace" => Action::Erase,
+            "Delete" => Action::ToggleBlack,
+            "Tab" => Action::NextWord,
+            "ArrowRight" => Action::MoveCaret(Arrow::Right),
+            "ArrowLeft" => Action::MoveCaret(Arrow::Left),
+            "ArrowUp" => Action::MoveCaret(Arrow::Up),
+            "ArrowDown" => Action::MoveCaret(Arrow::Down),
+            "Control" => Action::SwitchDirection,
+            _ => {
+                console_log(&ev.key());
+                return;
             }
-            "ArrowLeft" => {
-                ev.prevent_default();
-                if let Some(selected_cell) = caret_cell() {
-                    if selected_cell.0 > 0 {
-                        let new_cell = (selected_cell.0 - 1, selected_cell.1);
-                        click_cell(new_cell);
+        };
+
+        ev.prevent_default();
+        dispatch(action);
+    };
+
+    window_keydown_listener(cx, press_keydown);
+
+    let slot_pattern = crossword.slot_pattern();
+    let active_slot = store.selection.active_slot;
+    let active_pattern = move || active_slot().map(slot_pattern);
+
+    let symmetry_violations = crossword.symmetry_violations();
+    let symmetry_warning = move || {
+        let count = symmetry_violations().len();
+        (count > 0).then(|| format!("{} cells break the grid's symmetry", count))
+    };
+    let validity_warning = move || {
+        store
+            .validity
+            .with(|validity| Some(validity.to_string()).filter(|warning| !warning.is_empty()))
+    };
+
+    let seed_input = create_rw_signal(cx, String::new());
+    let run_generator = move |_| {
+        let seed = seed_input.get_untracked();
+        let seed = seed.trim();
+        let seed = if seed.is_empty() {
+            None
+        } else if let Ok(seed) = seed.parse() {
+            Some(seed)
+        } else {
+            store
+                .status
+                .set(Some(format!("\"{}\" isn't a valid seed", seed)));
+            return;
+        };
+
+        let job = Job::Generate {
+            saved: crossword.to_saved(),
+            config: store.generator.get_untracked(),
+            seed,
+        };
+        store.start_job(cx, job, Action::RunGenerator { seed });
+    };
+    let last_seed = move || store.last_seed.get().map(|seed| format!("Seed {}", seed));
+
+    let run_autofill = move |_| {
+        let job = Job::Autofill {
+            saved: crossword.to_saved(),
+            words: store.dictionary.list.with(WordList::to_text),
+        };
+        store.start_job(cx, job, Action::Autofill);
+    };
+
+    let job = store.job;
+    let is_running = move || job.with(Option::is_some);
+    let no_best = move || !job.with(|job| job.as_ref().is_some_and(|job| job.best.is_some()));
+    let progress = move || {
+        job.with(|job| {
+            job.as_ref().map(|job| match job.score {
+                Some(score) => format!(
+                    "{}: iteration {}, best score {:.3}",
+                    job.name, job.iteration, score
+                ),
+                None => format!("{}…", job.name),
+            })
+        })
+    };
+
+    let undo_disabled = move || !crossword.history.with(|h| h.can_undo());
+    let redo_disabled = move || !crossword.history.with(|h| h.can_redo());
+
+    view! { cx,
+        <div class="editor">
+            <div class="workspace">
+                <Crossword on:focusout=remove_selection/>
+                <Suggestions/>
+                <Clues/>
+            </div>
+            <PuzzleDetails id=id saved=move || crossword.to_saved()/>
+            <BoardSettings/>
+            <CellStyles/>
+            <WordListSettings pattern=active_pattern/>
+            <GeneratorSettings/>
+            <Candidates/>
+            <TemplateLibrary
+                saved=move || crossword.to_saved()
+                on_apply=move |template| dispatch(Action::ApplyTemplate(template))
+            />
+            <div class="toolbar">
+                <input
+                    type="text"
+                    class="seed"
+                    placeholder="Random seed"
+                    prop:value=seed_input
+                    on:input=move |ev| seed_input.set(event_target_value(&ev))
+                />
+                <button on:click=run_generator disabled=is_running>"Fill blacks"</button>
+                <span class="seed-used">{last_seed}</span>
+                <button on:click=run_autofill disabled=is_running>"Autofill"</button>
+                <button on:click=move |_| dispatch(Action::ClearUnlocked) disabled=is_running>
+                    "Clear unlocked letters"
+                </button>
+                {move || {
+                    is_running()
+                        .then(|| {
+                            view! { cx,
+                                <span class="job">
+                                    <span class="progress">{progress}</span>
+                                    <button on:click=move |_| store.stop_job()>"Cancel"</button>
+                                    <button on:click=move |_| store.take_best(cx) disabled=no_best>
+                                        "Take current best"
+                                    </button>
+                                </span>
+                            }
+                        })
+                }}
+                <button on:click=move |_| dispatch(Action::Undo) disabled=undo_disabled>"Undo"</button>
+                <button on:click=move |_| dispatch(Action::Redo) disabled=redo_disabled>"Redo"</button>
+                <span class="messages">{move || store.status.get()}</span>
+                <span class="messages">{symmetry_warning}</span>
+                <span class="messages">{validity_warning}</span>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+pub fn SymmetrySelect(cx: Scope) -> impl IntoView {
+    let store = use_context::<Store>(cx).expect("Parent did not provide store");
+    let symmetry = store.crossword.symmetry;
+    let dispatch = store.dispatch(cx);
+
+    let on_change = move |ev| {
+        let index = event_target_value(&ev).parse::<usize>().unwrap_or_default();
+        dispatch(Action::SetSymmetry(Symmetry::ALL[index]));
+    };
+
+    view! { cx,
+        <select on:change=on_change>
+            {Symmetry::ALL
+                .iter()
+                .enumerate()
+                .map(|(i, &option)| {
+                    view! { cx,
+                        <option value=i prop:selected=move || symmetry() == option>
+                            {option.name()}
+                        </option>
                     }
-                }
+                })
+                .collect::<Vec<_>>()}
+        </select>
+    }
+}
+
+#[component]
+pub fn GeneratorSettings(cx: Scope) -> impl IntoView {
+    let store = use_context::<Store>(cx).expect("Parent did not provide store");
+    let generator = store.generator;
+    let dispatch = store.dispatch(cx);
+
+    let choose_preset = move |ev| {
+        if let Some(preset) = event_target_value(&ev)
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| Preset::ALL.get(i))
+        {
+            dispatch(Action::ChoosePreset(*preset));
+        }
+    };
+
+    let histogram = move || {
+        let counts = store
+            .crossword
+            .grid
+            .with(|grid| generate::histogram(&find_runs(grid)));
+        view! { cx, <Histogram counts=counts/> }
+    };
+
+    let choose_strategy = move |ev| {
+        let index = event_target_value(&ev).parse::<usize>().unwrap_or_default();
+        dispatch(Action::SetGeneratorConfig(GeneratorConfig {
+            strategy: Strategy::ALL[index],
+            ..generator.get_untracked()
+        }));
+    };
+
+    let field = move |label: &'static str,
+                      get: fn(&GeneratorConfig) -> f32,
+                      set: fn(&mut GeneratorConfig, f32)| {
+        let on_change = move |ev| {
+            if let Ok(value) = event_target_value(&ev).parse::<f32>() {
+                let mut config = generator.get_untracked();
+                set(&mut config, value.max(0.0));
+                dispatch(Action::SetGeneratorConfig(config));
             }
-            "ArrowUp" => {
-                ev.prevent_default();
-                if let Some(selected_cell) = caret_cell() {
-                    if selected_cell.1 > 0 {
-                        let new_cell = (selected_cell.0, selected_cell.1 - 1);
-                        click_cell(new_cell);
+        };
+        view! { cx,
+            <label>
+                {label}
+                <input
+                    type="number"
+                    step="any"
+                    prop:value=move || generator.with(get).to_string()
+                    on:change=on_change
+                />
+            </label>
+        }
+    };
+
+    view! { cx,
+        <div class="details generator">
+            <label>
+                "Preset"
+                <select on:change=choose_preset>
+                    <option value="" selected>"Custom"</option>
+                    {Preset::ALL
+                        .iter()
+                        .enumerate()
+                        .map(|(i, preset)| view! { cx, <option value=i>{preset.name()}</option> })
+                        .collect::<Vec<_>>()}
+                </select>
+            </label>
+            <label>"Symmetry" <SymmetrySelect/></label>
+            <label>
+                "Strategy"
+                <select on:change=choose_strategy>
+                    {Strategy::ALL
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &option)| {
+                            view! { cx,
+                                <option
+                                    value=i
+                                    prop:selected=move || generator.with(|c| c.strategy == option)
+                                >
+                                    {option.name()}
+                                </option>
+                            }
+                        })
+                        .collect::<Vec<_>>()}
+                </select>
+            </label>
+            {field("Avg word length from", |c| c.avg_word_len.start, |c, v| c.avg_word_len.start = v)}
+            {field("Avg word length to", |c| c.avg_word_len.end, |c, v| c.avg_word_len.end = v)}
+            {field("Words from", |c| c.word_count.start as f32, |c, v| c.word_count.start = v as usize)}
+            {field("Words to", |c| c.word_count.end as f32, |c, v| c.word_count.end = v as usize)}
+            {field("Black % from", |c| c.black_ratio.start * 100.0, |c, v| c.black_ratio.start = v / 100.0)}
+            {field("Black % to", |c| c.black_ratio.end * 100.0, |c, v| c.black_ratio.end = v / 100.0)}
+            {field("Min word length", |c| c.min_word_len as f32, |c, v| c.min_word_len = (v as usize).max(2))}
+            {field("Max word length", |c| c.max_word_len as f32, |c, v| c.max_word_len = (v as usize).max(2))}
+            {field("Iterations", |c| c.max_iters as f32, |c, v| c.max_iters = (v as usize).max(1))}
+            {field("Candidates", |c| c.candidates as f32, |c, v| c.candidates = (v as usize).max(1))}
+            <LengthTargets/>
+            <div class="word-lengths">"Word lengths now" {histogram}</div>
+        </div>
+    }
+}
+
+/// Edits `GeneratorConfig::length_targets`. Empty upper bounds mean no limit.
+#[component]
+pub fn LengthTargets(cx: Scope) -> impl IntoView {
+    let store = use_context::<Store>(cx).expect("Parent did not provide store");
+    let generator = store.generator;
+    let dispatch = store.dispatch(cx);
+    // Only rebuilds the rows when one is added or removed, so editing a value
+    // doesn't steal focus from the next input.
+    let len = create_memo(cx, move |_| generator.with(|c| c.length_targets.len()));
+
+    let update = move |change: &dyn Fn(&mut Vec<LengthTarget>)| {
+        let mut config = generator.get_untracked();
+        change(&mut config.length_targets);
+        dispatch(Action::SetGeneratorConfig(config));
+    };
+
+    let number = move |i: usize,
+                       get: fn(&LengthTarget) -> usize,
+                       set: fn(&mut LengthTarget, usize)| {
+        let value = move || {
+            generator
+                .with(|c| c.length_targets.get(i).map(get))
+                .filter(|&value| value != usize::MAX)
+                .map(|value| value.to_string())
+                .unwrap_or_default()
+        };
+        let on_change = move |ev| {
+            let value = event_target_value(&ev);
+            let value = match value.trim() {
+                "" => Some(usize::MAX),
+                value => value.parse().ok(),
+            };
+            if let Some(value) = value {
+                update(&|targets| {
+                    if let Some(target) = targets.get_mut(i) {
+                        set(target, value);
                     }
-                }
-            }
-            "ArrowDown" => {
-                ev.prevent_default();
-                if let Some(selected_cell) = caret_cell() {
-                    let new_cell = (selected_cell.0, selected_cell.1 + 1);
-                    click_cell(new_cell);
-                }
-            }
-            "Control" => {
-                ev.prevent_default();
-                if let Some(selected_cell) = caret_cell() {
-                    click_cell(selected_cell);
-                }
-            }
-            _ => {
-                console_log(&ev.key());
+                });
             }
+        };
+        view! { cx,
+            <input type="number" min="0" placeholder="any" prop:value=value on:change=on_change/>
         }
     };
 
-    window_event_listener("keydown", press_keydown);
+    let row = move |i: usize| {
+        let is_share = move || {
+            generator.with(|c| {
+                matches!(
+                    c.length_targets.get(i),
+                    Some(LengthTarget {
+                        limit: Limit::Share(_),
+                        ..
+                    })
+                )
+            })
+        };
+        let choose_limit = move |ev| {
+            let limit = match event_target_value(&ev).as_str() {
+                "share" => Limit::Share(0.0..1.0),
+                _ => Limit::Count(0..usize::MAX),
+            };
+            update(&|targets| {
+                if let Some(target) = targets.get_mut(i) {
+                    target.limit = limit.clone();
+                }
+            });
+        };
+
+        view! { cx,
+            <li>
+                "Words of "
+                {number(i, |t| *t.lengths.start(), |t, v| t.lengths = v..=*t.lengths.end())}
+                " to "
+                {number(i, |t| *t.lengths.end(), |t, v| t.lengths = *t.lengths.start()..=v)}
+                " letters: "
+                {number(i, |t| t.limit.bounds().start, |t, v| t.limit.set_bounds(v..t.limit.bounds().end))}
+                " to "
+                {number(i, |t| t.limit.bounds().end, |t, v| t.limit.set_bounds(t.limit.bounds().start..v))}
+                <select on:change=choose_limit>
+                    <option value="count" prop:selected=move || !is_share()>"words"</option>
+                    <option value="share" prop:selected=is_share>"% of words"</option>
+                </select>
+                <button on:click=move |_| update(&|targets| { targets.remove(i); })>"Remove"</button>
+            </li>
+        }
+    };
 
-    let fill_blacks = move |_| {
-        grid.update(move |grid| {
-            let mut grid_generator = GridGenerator::new(&*grid);
+    let add = move |_| {
+        update(&|targets| {
+            targets.push(LengthTarget {
+                lengths: 3..=3,
+                limit: Limit::Count(0..usize::MAX),
+            })
+        })
+    };
 
-            grid_generator.place_blacks(5.2..5.5, 40..73);
+    view! { cx,
+        <div class="length-targets">
+            "Word length targets"
+            <ul>{move || (0..len.get()).map(row).collect::<Vec<_>>()}</ul>
+            <button on:click=add>"Add target"</button>
+        </div>
+    }
+}
 
-            let cells = grid_generator.cells();
-            for (i, is_black) in cells.into_iter().enumerate() {
-                let coord = (i % grid.width, i / grid.width);
-                grid.black(coord, cx, is_black);
-            }
-        });
+#[component]
+pub fn Candidates(cx: Scope) -> impl IntoView {
+    let store = use_context::<Store>(cx).expect("Parent did not provide store");
+    let candidates = store.candidates;
+    let dispatch = store.dispatch(cx);
+
+    let thumbnail = move |i: usize, candidate: Candidate| {
+        view! { cx,
+            <li on:click=move |_| dispatch(Action::PickCandidate(i))>
+                <Thumbnail width=candidate.width cells=candidate.cells.clone()/>
+                <span>
+                    {format!("{} words, avg {:.1}", candidate.word_count, candidate.avg_word_len)}
+                </span>
+                <Histogram counts=candidate.histogram.clone()/>
+            </li>
+        }
     };
 
     view! { cx,
-        <div class="app">
-            <div class="content">
-                <Header/>
-                <Crossword on:focusout=remove_selection/>
-            </div>
-            <button on:click=fill_blacks>"Fill blacks"</button>
-            <Dialog/>
-        </div>
+        <ol class="candidates">
+            {move || {
+                candidates
+                    .get()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, candidate)| thumbnail(i, candidate))
+                    .collect::<Vec<_>>()
+            }}
+        </ol>
     }
 }
 
+/// A bar for each word length, indexed by length.
+#[component]
+pub fn Histogram(cx: Scope, counts: Vec<usize>) -> impl IntoView {
+    let most = counts.iter().copied().max().unwrap_or(0).max(1);
+    let bars = counts
+        .iter()
+        .enumerate()
+        .filter(|&(_, &count)| count > 0)
+        .map(|(len, &count)| {
+            let title = format!("{} words of {} letters", count, len);
+            let style = format!("height:{}%", count * 100 / most);
+            view! { cx,
+                <li style=style title=title>
+                    <span>{len}</span>
+                </li>
+            }
+        })
+        .collect::<Vec<_>>();
+
+    view! { cx, <ol class="histogram">{bars}</ol> }
+}
+
+/// A small picture of a pattern of black squares.
+#[component]
+pub fn Thumbnail(cx: Scope, width: usize, cells: Vec<bool>) -> impl IntoView {
+    let style = format!("--columns:{}", width);
+    let cells = cells
+        .into_iter()
+        .map(|is_black| view! { cx, <span class:black=is_black></span> })
+        .collect::<Vec<_>>();
+
+    view! { cx, <div class="thumbnail" style=style>{cells}</div> }
+}
+
+/// Like `window_event_listener`, but removes the listener again when `cx` is
+/// disposed so that reopening the editor doesn't stack handlers.
+fn window_keydown_listener(cx: Scope, cb: impl Fn(KeyboardEvent) + 'static) {
+    let cb = Closure::<dyn Fn(KeyboardEvent)>::new(cb).into_js_value();
+    _ = window().add_event_listener_with_callback("keydown", cb.unchecked_ref());
+    on_cleanup(cx, move || {
+        _ = window().remove_event_listener_with_callback("keydown", cb.unchecked_ref());
+    });
+}
+
 #[component]
 pub fn Header(cx: Scope) -> impl IntoView {
     view! { cx,
@@ -691,8 +2034,9 @@ pub fn Header(cx: Scope) -> impl IntoView {
 
 #[component]
 pub fn Crossword(cx: Scope) -> impl IntoView {
-    let crossword = use_context::<Crossword>(cx).expect("Parent did not provide crossword");
-    let selection = use_context::<Selection>(cx).expect("Parent did not provide selection");
+    let store = use_context::<Store>(cx).expect("Parent did not provide store");
+    let crossword = store.crossword;
+    let selection = store.selection;
     let style = crossword.style();
     let display_cells = crossword.display_cells();
     let cells = Signal::derive(cx, display_cells);
@@ -705,7 +2049,22 @@ pub fn Crossword(cx: Scope) -> impl IntoView {
 
     create_effect(cx, move |_| log(is_across));
 
-    let click_cell = selection.click_cell(crossword.get_slot(), crossword.cell_exists());
+    let bars_at = crossword.bars_at();
+    let dispatch = store.dispatch(cx);
+    let click_cell = move |cell| dispatch(Action::ClickCell(cell));
+    let can_bar = store.crossword.can_bar();
+    let click_edge = move |cell, ev: &MouseEvent| {
+        let edge = store
+            .editing_bars
+            .get_untracked()
+            .then(|| nearest_edge(ev))
+            .flatten()
+            .filter(|&edge| can_bar(cell, edge));
+        if let Some(edge) = edge {
+            dispatch(Action::ToggleBar { cell, edge });
+        }
+        edge.is_some()
+    };
 
     view! { cx,
         <div class="crossword" style=style across-entry-mode=is_across>
@@ -713,7 +2072,9 @@ pub fn Crossword(cx: Scope) -> impl IntoView {
                 cells=cells
                 answer_id_at=answer_id_at
                 corners_at=corners_at
+                bars_at=bars_at
                 click_cell=click_cell
+                click_edge=click_edge
                 caret_cell=caret_cell
             />
             <Caret position=caret_cell hide=hide_caret/>
@@ -722,19 +2083,172 @@ pub fn Crossword(cx: Scope) -> impl IntoView {
     }
 }
 
+/// The edge of the clicked element that the click was within `EDGE_MARGIN`
+/// of, as a share of the element's size.
+fn nearest_edge(ev: &MouseEvent) -> Option<Edge> {
+    let rect = ev
+        .current_target()?
+        .dyn_into::<Element>()
+        .ok()?
+        .get_bounding_client_rect();
+    let x = (ev.client_x() as f64 - rect.left()) / rect.width();
+    let y = (ev.client_y() as f64 - rect.top()) / rect.height();
+
+    [
+        (x, Edge::Left),
+        (1.0 - x, Edge::Right),
+        (y, Edge::Top),
+        (1.0 - y, Edge::Bottom),
+    ]
+    .into_iter()
+    .filter(|&(distance, _)| distance < EDGE_MARGIN)
+    .min_by(|a, b| a.0.total_cmp(&b.0))
+    .map(|(_, edge)| edge)
+}
+
+#[component]
+pub fn Suggestions(cx: Scope) -> impl IntoView {
+    let store = use_context::<Store>(cx).expect("Parent did not provide store");
+    let dictionary = store.dictionary;
+    let active_slot = store.selection.active_slot;
+    let slot_pattern = store.crossword.slot_pattern();
+    let crossings = store.crossword.crossings();
+    let dispatch = store.dispatch(cx);
+
+    let suggestions = create_memo(cx, move |_| {
+        let Some(slot) = active_slot() else {
+            return Vec::new();
+        };
+        let pattern = slot_pattern(slot);
+        let crossings = crossings(slot);
+
+        dictionary.list.with(|list| {
+            list.suggestions(&pattern, &crossings)
+                .into_iter()
+                .take(SUGGESTION_LIMIT)
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+    });
+
+    view! { cx,
+        <ol class="suggestions">
+            <For
+                each=suggestions
+                key=|suggestion| suggestion.word.clone()
+                view=move |cx, suggestion: ScoredWord| {
+                    let word = suggestion.word.clone();
+                    // Filling on mousedown keeps focus, and so the selection, in the grid.
+                    let fill = move |ev: MouseEvent| {
+                        ev.prevent_default();
+                        dispatch(Action::FillSlot(word.clone()));
+                    };
+                    view! { cx,
+                        <li on:mousedown=fill>
+                            <span>{suggestion.word}</span>
+                            <span class="score">{suggestion.score}</span>
+                        </li>
+                    }
+                }
+            />
+        </ol>
+    }
+}
+
+#[component]
+pub fn Clues(cx: Scope) -> impl IntoView {
+    view! { cx,
+        <div class="clues">
+            <ClueList is_across=true/>
+            <ClueList is_across=false/>
+        </div>
+    }
+}
+
+#[component]
+pub fn ClueList(cx: Scope, is_across: bool) -> impl IntoView {
+    let store = use_context::<Store>(cx).expect("Parent did not provide store");
+    let clue_rows = store.crossword.clue_rows();
+    let active_slot = store.selection.active_slot;
+    let dispatch = store.dispatch(cx);
+
+    view! { cx,
+        <div class="clue-list">
+            <h3>{if is_across { "Across" } else { "Down" }}</h3>
+            <ol>
+                <For
+                    each=move || clue_rows(is_across)
+                    key=|row| row.clone()
+                    view=move |cx, row: ClueRow| {
+                        let ClueRow { number, head, clue, .. } = row;
+                        let is_locked = {
+                            let row = row.clone();
+                            move || row.is_locked()
+                        };
+                        let is_active = move || {
+                            active_slot.with(|s| {
+                                s.is_some_and(|s| s.head == head && s.is_across == is_across)
+                            })
+                        };
+                        let item = create_node_ref::<Li>(cx);
+                        create_effect(cx, move |_| {
+                            if let Some(item) = is_active().then(|| item.get()).flatten() {
+                                let mut options = ScrollIntoViewOptions::new();
+                                options.block(ScrollLogicalPosition::Nearest);
+                                item.scroll_into_view_with_scroll_into_view_options(&options);
+                            }
+                        });
+                        let select = move |_| dispatch(Action::SelectWord { head, is_across });
+                        let set_clue = move |ev| {
+                            dispatch(Action::SetClue {
+                                head,
+                                is_across,
+                                clue: event_target_value(&ev),
+                            })
+                        };
+                        view! { cx,
+                            <li
+                                node_ref=item
+                                class:active=is_active
+                                class:locked=is_locked
+                                on:click=select
+                            >
+                                <span class="clue-number">{number}</span>
+                                // Selecting the word would move focus to the grid.
+                                <input
+                                    type="text"
+                                    prop:value=clue
+                                    on:change=set_clue
+                                    on:click=|ev| ev.stop_propagation()
+                                />
+                                <span class="clue-pattern">{move || row.pattern()}</span>
+                            </li>
+                        }
+                    }
+                />
+            </ol>
+        </div>
+    }
+}
+
 #[component]
-pub fn Cells<A, O, C>(
+pub fn Cells<A, O, E, C, B>(
     cx: Scope,
     cells: Signal<Vec<((usize, usize), Option<Cell>)>>,
     answer_id_at: A,
     corners_at: C,
+    bars_at: B,
     click_cell: O,
+    /// Handles a click near the cell's edge, returning whether it did.
+    click_edge: E,
     caret_cell: Signal<Option<(usize, usize)>>,
 ) -> impl IntoView
 where
     A: Fn((usize, usize)) -> Option<usize> + 'static + Copy,
     O: Fn((usize, usize)) + 'static + Copy,
+    E: Fn((usize, usize), &MouseEvent) -> bool + 'static + Copy,
     C: Fn((usize, usize)) -> Corners + 'static + Copy,
+    B: Fn((usize, usize)) -> Bars + 'static + Copy,
 {
     view! { cx,
         <For
@@ -744,18 +2258,26 @@ where
                 cell.map(|cell| {
                     let answer_id = Signal::derive(cx, move || answer_id_at(position));
                     let corners = Signal::derive(cx, move || corners_at(position));
+                    let bars = Signal::derive(cx, move || bars_at(position));
                     let on_mouseover = move |ev: MouseEvent| {
-                        if ev.buttons() == 1 && caret_cell().map_or(true, |c| c != position) {
+                        if ev.buttons() == 1 && caret_cell() != Some(position) {
                             click_cell(position);
                         }
                     };
                     view! { cx,
                         <Letter
-                            on:click=move |ev| click_cell(position)
+                            on:click=move |ev| {
+                                if !click_edge(position, &ev) {
+                                    click_cell(position);
+                                }
+                            }
                             on:mouseover=on_mouseover
                             letter=cell.letter.into()
+                            cell_style=cell.style.into()
+                            locked=cell.locked.into()
                             answer_id=answer_id
                             corners=corners
+                            bars=bars
                             position=position
                         />
                     }
@@ -768,20 +2290,23 @@ where
 #[component]
 pub fn Letter(
     cx: Scope,
-    letter: Signal<char>,
+    letter: Signal<String>,
+    cell_style: Signal<CellStyle>,
+    locked: Signal<bool>,
     answer_id: Signal<Option<usize>>,
     corners: Signal<Corners>,
+    bars: Signal<Bars>,
     position: (usize, usize),
 ) -> impl IntoView {
     let entered = create_rw_signal(cx, ());
 
-    let is_entering = create_memo::<(Option<char>, bool)>(cx, move |prev_letter| {
+    let is_entering = create_memo::<(Option<String>, bool)>(cx, move |prev_letter| {
         entered();
         let letter = letter();
 
-        match prev_letter.and_then(|l| l.0) {
+        match prev_letter.and_then(|l| l.0.as_ref()) {
             None => (Some(letter), true),
-            Some(prev_letter) if letter != prev_letter => (Some(letter), true),
+            Some(prev_letter) if letter != *prev_letter => (Some(letter), true),
             _ => (None, false),
         }
     });
@@ -795,13 +2320,14 @@ pub fn Letter(
     let selection = use_context::<Selection>(cx).expect("Selection should be provided");
     let caret_cell = selection.caret_cell();
     let is_caret_cell = create_selector(cx, caret_cell);
+    let is_editing_cell = is_caret_cell.clone();
     let node_ref = create_node_ref::<Div>(cx);
     create_effect(cx, move |_| {
         if is_caret_cell(Some(position)) {
             let node_ref = node_ref.get().expect("Should have node");
             let already_active = document()
                 .active_element()
-                .map_or(false, |e| node_ref.is_equal_node(Some(&e)));
+                .is_some_and(|e| node_ref.is_equal_node(Some(&e)));
 
             if !already_active {
                 node_ref.focus().unwrap();
@@ -809,12 +2335,64 @@ pub fn Letter(
         }
     });
 
-    let style = move || format!("--x:{};--y:{}", position.0, position.1);
+    let length = move || letter.with(|l| l.chars().count());
+    let shade = move || cell_style.with(|style| style.shade.clone());
+    let style = move || {
+        let mut style = format!(
+            "--x:{};--y:{};--length:{}",
+            position.0,
+            position.1,
+            length()
+        );
+        if let Some(shade) = shade() {
+            style.push_str(&format!(";--shade:{}", shade));
+        }
+        style
+    };
+    let is_rebus = move || length() > 1;
+    let is_circled = move || cell_style.with(|style| style.circle);
+    let is_shaded = move || shade().is_some();
+    let is_bold = move || cell_style.with(|style| style.bold);
+
+    let store = use_context::<Store>(cx).expect("Parent did not provide store");
+    let validity = store.validity;
+    let is_invalid = move || validity.with(|validity| validity.flags(position));
+
+    let dispatch = store.dispatch(cx);
+    let rebus_editor = move || {
+        (store.rebus.get() && is_editing_cell(Some(position))).then(|| {
+            let input_ref = create_node_ref::<Input>(cx);
+            input_ref.on_load(cx, |input| {
+                request_animation_frame(move || _ = input.focus());
+            });
+            let on_keydown = move |ev: KeyboardEvent| match ev.key().as_str() {
+                "Enter" => {
+                    ev.prevent_default();
+                    dispatch(Action::EnterRebus(event_target_value(&ev)));
+                }
+                "Escape" => dispatch(Action::CloseRebus),
+                _ => (),
+            };
+
+            view! { cx,
+                <input
+                    type="text"
+                    class="rebus-editor"
+                    _ref=input_ref
+                    value=letter.get_untracked().trim().to_owned()
+                    on:keydown=on_keydown
+                    on:blur=move |_| dispatch(Action::CloseRebus)
+                />
+            }
+        })
+    };
 
     let corner_bottom_left = move || corners().bottom_left;
     let corner_bottom_right = move || corners().bottom_right;
     let corner_top_left = move || corners().top_left;
     let corner_top_right = move || corners().top_right;
+    let bar_right = move || bars().right;
+    let bar_bottom = move || bars().bottom;
 
     view! { cx,
         <div
@@ -826,11 +2404,24 @@ pub fn Letter(
             class:corner-bottom-right=corner_bottom_right
             class:corner-top-left=corner_top_left
             class:corner-top-right=corner_top_right
+            class:bar-right=bar_right
+            class:bar-bottom=bar_bottom
+            class:invalid=is_invalid
+            class:circled=is_circled
+            class:shaded=is_shaded
+            class:bold=is_bold
+            class:locked=locked
         >
             <span class="answer-id">{answer_id}</span>
-            <span class="letter" class:enter=is_entering on:animationend=after_enter>
+            <span
+                class="letter"
+                class:enter=is_entering
+                class:rebus=is_rebus
+                on:animationend=after_enter
+            >
                 {letter}
             </span>
+            {rebus_editor}
         </div>
     }
 }
@@ -886,7 +2477,7 @@ where
     };
 
     let has_position = move || position().is_some();
-    let across = move || position().map_or(false, |v| v.is_across);
+    let across = move || position().is_some_and(|v| v.is_across);
 
     view! { cx,
         <Show when=has_position fallback=|_| ()>
@@ -986,29 +2577,135 @@ pub fn Dialog(cx: Scope) -> impl IntoView {
     }
 }
 
+/// Adds and removes rows and columns on each edge of the grid.
 #[component]
 pub fn BoardSettings(cx: Scope) -> impl IntoView {
-    // let set_board = use_context::<WriteSignal<Board>>(cx).unwrap();
-    //
-    // let add_row = move |_| set_board.update(|b| b.add_row(cx));
-    // let pop_row = move |_| set_board.update(|b| b.pop_row());
-    // let add_column = move |_| set_board.update(|b| b.add_column(cx));
-    // let pop_column = move |_| set_board.update(|b| b.pop_column());
-    //
-    // view! { cx,
-    //     <div class="board-settings">
-    //         <button on:click=add_row>"increase row"</button>
-    //         <button on:click=pop_row>"descrease row"</button>
-    //         <button on:click=add_column>"increase column"</button>
-    //         <button on:click=pop_column>"decrease column"</button>
-    //     </div>
-    // }
+    let store = use_context::<Store>(cx).expect("Parent did not provide store");
+    let grid = store.crossword.grid;
+    let dispatch = store.dispatch(cx);
+    let size = move || grid.with(|grid| (grid.cols(), grid.rows()));
+
+    let edge = move |edge: Edge| {
+        let is_rows = matches!(edge, Edge::Top | Edge::Bottom);
+        let length = move || {
+            let (width, height) = size();
+            if is_rows {
+                height
+            } else {
+                width
+            }
+        };
+        let noun = if is_rows { "row" } else { "column" };
+        let is_largest = move || length() >= MAX_SIZE;
+        let is_smallest = move || length() <= MIN_SIZE;
+
+        view! { cx,
+            <span>
+                {edge.name()}
+                <button
+                    title=format!("Add a {}", noun)
+                    disabled=is_largest
+                    on:click=move |_| dispatch(Action::Resize { edge, grow: true })
+                >
+                    "+"
+                </button>
+                <button
+                    title=format!("Remove a {}", noun)
+                    disabled=is_smallest
+                    on:click=move |_| dispatch(Action::Resize { edge, grow: false })
+                >
+                    "−"
+                </button>
+            </span>
+        }
+    };
+
+    view! { cx,
+        <div class="toolbar board-settings">
+            <span>
+                {move || {
+                    let (width, height) = size();
+                    format!("{}×{}", width, height)
+                }}
+            </span>
+            {Edge::ALL.into_iter().map(edge).collect::<Vec<_>>()}
+            <label title="Click near a cell's edge to add or remove the bar there">
+                <input
+                    type="checkbox"
+                    prop:checked=store.editing_bars
+                    on:change=move |ev| store.editing_bars.set(event_target_checked(&ev))
+                />
+                "Edit bars"
+            </label>
+        </div>
+    }
 }
 
-#[derive(Clone, Default, Debug, PartialEq)]
+/// Circles, shades, bolds or locks the caret cell or the whole active word.
+#[component]
+pub fn CellStyles(cx: Scope) -> impl IntoView {
+    let store = use_context::<Store>(cx).expect("Parent did not provide store");
+    let dispatch = store.dispatch(cx);
+    let whole_slot = create_rw_signal(cx, false);
+
+    let button = move |label: &'static str, shortcut: &'static str, mark: fn(String) -> Mark| {
+        view! { cx,
+            <button
+                title=format!("{} (Alt+{}, with Shift for the whole word)", label, shortcut)
+                // Keeps focus in the grid, which would otherwise clear the selection.
+                on:mousedown=|ev| ev.prevent_default()
+                on:click=move |_| {
+                    dispatch(Action::ToggleMark {
+                        mark: mark(store.shade.get_untracked()),
+                        whole_slot: whole_slot.get_untracked(),
+                    })
+                }
+            >
+                {label}
+            </button>
+        }
+    };
+
+    view! { cx,
+        <div class="toolbar cell-styles">
+            {button("Circle", "C", |_| Mark::Circle)}
+            {button("Shade", "S", Mark::Shade)}
+            <input
+                type="color"
+                title="Shade colour"
+                prop:value=store.shade
+                on:input=move |ev| store.shade.set(event_target_value(&ev))
+            />
+            {button("Bold", "B", |_| Mark::Bold)}
+            <button
+                title="Lock (Alt+L, with Shift for the whole word)"
+                on:mousedown=|ev| ev.prevent_default()
+                on:click=move |_| {
+                    dispatch(Action::ToggleLock {
+                        whole_slot: whole_slot.get_untracked(),
+                    })
+                }
+            >
+                "Lock"
+            </button>
+            <label on:mousedown=|ev| ev.prevent_default()>
+                <input
+                    type="checkbox"
+                    prop:checked=whole_slot
+                    on:change=move |ev| whole_slot.set(event_target_checked(&ev))
+                />
+                "Whole word"
+            </label>
+        </div>
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Word {
-    answer: Vec<RwSignal<char>>,
-    clue: String,
+    answer: Vec<RwSignal<String>>,
+    /// Whether each of the word's cells is locked.
+    locked: Vec<RwSignal<bool>>,
+    clue: RwSignal<String>,
 }
 
 impl Display for Word {
@@ -1021,6 +2718,33 @@ impl Display for Word {
     }
 }
 
+/// A word as listed in the clue panel, numbered the same way as the grid.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct ClueRow {
+    number: usize,
+    head: (usize, usize),
+    is_across: bool,
+    answer: Vec<RwSignal<String>>,
+    locked: Vec<RwSignal<bool>>,
+    clue: RwSignal<String>,
+}
+
+impl ClueRow {
+    fn is_locked(&self) -> bool {
+        self.locked.iter().all(|locked| locked.get())
+    }
+
+    fn pattern(&self) -> String {
+        self.answer
+            .iter()
+            .map(|letter| match letter.get().as_str() {
+                " " => "_".to_owned(),
+                letter => letter.to_uppercase(),
+            })
+            .collect()
+    }
+}
+
 #[derive(PartialEq, Debug, Default)]
 pub struct Answers {
     answer_keys: HashMap<(usize, usize), usize>,
@@ -1053,7 +2777,7 @@ impl Answers {
         }
     }
 
-    fn new(this: Option<&Self>, grid: &Grid) -> Self {
+    fn new(cx: Scope, this: Option<&Self>, grid: &Grid) -> Self {
         console_log("creating answers");
         let mut answers = Vec::<Head>::with_capacity(this.map_or(0, |t| t.answers.len()));
         let runs = find_runs(grid);
@@ -1078,24 +2802,26 @@ impl Answers {
                 }
             };
 
+            let cells = (0..length)
+                .map(|i| {
+                    let char_coord = if is_across {
+                        (coord.0 + i, coord.1)
+                    } else {
+                        (coord.0, coord.1 + i)
+                    };
+                    grid.get(char_coord).and_then(|&c| c).unwrap()
+                })
+                .collect::<Vec<_>>();
             let word = Word {
-                answer: (0..length)
-                    .map(|i| {
-                        let char_coord = if is_across {
-                            (coord.0 + i, coord.1)
-                        } else {
-                            (coord.0, coord.1 + i)
-                        };
-                        grid.char_at(char_coord).unwrap()
-                    })
-                    .collect(),
+                answer: cells.iter().map(|c| c.letter).collect(),
+                locked: cells.iter().map(|c| c.locked).collect(),
                 clue: this
                     .and_then(|t| {
                         t.get(coord)
                             .and_then(|a| a.word(is_across).as_ref())
-                            .map(|w| w.clue.clone())
+                            .map(|w| w.clue)
                     })
-                    .unwrap_or("Enter a clue".to_owned()),
+                    .unwrap_or_else(|| create_rw_signal(cx, "Enter a clue".to_owned())),
             };
 
             *new_head.word_mut(is_across) = Some(word);
@@ -1146,6 +2872,12 @@ pub trait BoolMatrix: Copy {
     fn rows(self) -> usize;
     fn cols(self) -> usize;
     fn at(self, cell: (usize, usize)) -> bool;
+
+    /// Whether a bar on the right of `cell`, or its bottom when not
+    /// `is_across`, ends the word there.
+    fn barred(self, cell: (usize, usize), is_across: bool) -> bool {
+        false
+    }
 }
 
 fn find_runs<M>(m: M) -> Vec<(usize, usize, usize, bool)>
@@ -1164,7 +2896,7 @@ where
                 let x_start = x;
                 let mut length = 1;
                 x += 1;
-                while x < n_cols && m.at((x, y)) {
+                while x < n_cols && m.at((x, y)) && !m.barred((x - 1, y), true) {
                     length += 1;
                     x += 1;
                 }
@@ -1185,7 +2917,7 @@ where
                 let y_start = y;
                 let mut length = 1;
                 y += 1;
-                while y < n_rows && m.at((x, y)) {
+                while y < n_rows && m.at((x, y)) && !m.barred((x, y - 1), false) {
                     length += 1;
                     y += 1;
                 }
@@ -1235,27 +2967,486 @@ pub enum SlotDirection {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::words::{WordList, DEFAULT_LIST};
+
+    fn with_store(f: impl FnOnce(Store, &dyn Fn(Action)) + 'static) {
+        let runtime = create_runtime();
+        run_scope(runtime, |cx| {
+            let dictionary = Dictionary::from_list(cx, WordList::parse(DEFAULT_LIST));
+            let store = Store::new(
+                cx,
+                Crossword::from_grid(cx, Grid::new(cx, DEFAULT_WIDTH, DEFAULT_HEIGHT)),
+                dictionary,
+            );
+            let dispatch = store.dispatch(cx);
+            f(store, &dispatch);
+        });
+        runtime.dispose();
+    }
 
-    // #[test]
-    // fn create_crossword() {
-    //     let mut crossword = Crossword::new();
-    //
-    //     crossword.add_column_right();
-    //     crossword.add_column_right();
-    //     crossword.add_column_left();
-    //
-    //     crossword.add_row_bottom();
-    //     crossword.add_row_bottom();
-    //     crossword.add_row_bottom();
-    //
-    //     crossword.toggle_cell((0, 0));
-    //     crossword.toggle_cell((0, 1));
-    //     crossword.toggle_cell((1, 1));
-    //     crossword.toggle_cell((2, 1));
-    //
-    //     dbg!(crossword.answer_keys);
-    //     dbg!(crossword.answers);
-    //     assert!(false)
-    //     // assert_eq!(crossword.answers.len(), 2);
-    // }
+    fn row(store: Store, y: usize, len: usize) -> String {
+        let letter_at = store.crossword.letter_at();
+        (0..len)
+            .map(|x| letter_at((x, y)).unwrap_or_else(|| " ".to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn typing_a_word_is_undone_in_one_step() {
+        with_store(|store, dispatch| {
+            dispatch(Action::ClickCell((0, 0)));
+            for letter in "cat".chars() {
+                dispatch(Action::EnterLetter(letter));
+            }
+            assert_eq!(row(store, 0, 4), "cat ");
+            assert_eq!(store.selection.caret_cell()(), Some((3, 0)));
+
+            dispatch(Action::Undo);
+            assert_eq!(row(store, 0, 4), "    ");
+            assert_eq!(store.selection.caret_cell()(), Some((0, 0)));
+
+            dispatch(Action::Redo);
+            assert_eq!(row(store, 0, 4), "cat ");
+        });
+    }
+
+    #[test]
+    fn rebus_entry_writes_several_letters_into_one_cell() {
+        with_store(|store, dispatch| {
+            dispatch(Action::ClickCell((0, 0)));
+            dispatch(Action::EnterLetter('b'));
+            dispatch(Action::OpenRebus);
+            assert!(store.rebus.get());
+            dispatch(Action::EnterRebus(" he-art.".to_owned()));
+            assert!(!store.rebus.get());
+            assert_eq!(row(store, 0, 3), "bheart ");
+            assert_eq!(store.selection.caret_cell()(), Some((2, 0)));
+            assert_eq!(
+                store.crossword.to_saved().letters((1, 0)).as_deref(),
+                Some("heart")
+            );
+
+            dispatch(Action::Undo);
+            assert_eq!(row(store, 0, 3), "   ");
+        });
+    }
+
+    #[test]
+    fn marks_toggle_on_the_caret_cell_or_whole_word() {
+        with_store(|store, dispatch| {
+            let style = |cell| store.crossword.to_saved().style(cell);
+            dispatch(Action::ClickCell((1, 0)));
+            dispatch(Action::ToggleMark {
+                mark: Mark::Circle,
+                whole_slot: false,
+            });
+            assert!(style((1, 0)).circle);
+            assert!(!style((0, 0)).circle);
+
+            // Only some of the word is circled, so this circles the rest.
+            dispatch(Action::ToggleMark {
+                mark: Mark::Circle,
+                whole_slot: true,
+            });
+            assert!(style((0, 0)).circle && style((14, 0)).circle);
+            dispatch(Action::ToggleMark {
+                mark: Mark::Circle,
+                whole_slot: true,
+            });
+            assert!(style((1, 0)).is_plain());
+
+            dispatch(Action::ToggleMark {
+                mark: Mark::Shade("#ff0000".to_owned()),
+                whole_slot: false,
+            });
+            assert_eq!(style((1, 0)).shade.as_deref(), Some("#ff0000"));
+            dispatch(Action::Undo);
+            assert!(style((1, 0)).is_plain());
+        });
+    }
+
+    #[test]
+    fn bars_split_words_and_can_be_undone() {
+        with_store(|store, dispatch| {
+            let across_len = |cell| store.crossword.get_slot()(cell, true).map(|slot| slot.len);
+
+            dispatch(Action::ToggleBar {
+                cell: (4, 0),
+                edge: Edge::Right,
+            });
+            assert_eq!(across_len((0, 0)), Some(5));
+            assert_eq!(across_len((5, 0)), Some(10));
+            assert_eq!(store.crossword.get_slot()((0, 0), false).unwrap().len, 15);
+            let saved = store.crossword.to_saved();
+            assert!(saved.bars((4, 0)).right);
+            assert_eq!(saved.entries().len(), 31);
+
+            // The same bar, from the cell on its other side.
+            dispatch(Action::ToggleBar {
+                cell: (5, 0),
+                edge: Edge::Left,
+            });
+            assert_eq!(across_len((0, 0)), Some(15));
+
+            // The outside of the grid can't hold a bar, so isn't an undo step.
+            dispatch(Action::ToggleBar {
+                cell: (0, 0),
+                edge: Edge::Left,
+            });
+            assert!(!store.crossword.can_bar()((0, 0), Edge::Left));
+
+            dispatch(Action::Undo);
+            assert_eq!(across_len((0, 0)), Some(5));
+            dispatch(Action::Undo);
+            assert_eq!(across_len((0, 0)), Some(15));
+        });
+    }
+
+    #[test]
+    fn locked_cells_survive_clearing_and_stay_white() {
+        with_store(|store, dispatch| {
+            let saved = move || store.crossword.to_saved();
+            dispatch(Action::ClickCell((0, 0)));
+            for letter in "cat".chars() {
+                dispatch(Action::EnterLetter(letter));
+            }
+            dispatch(Action::ToggleLock { whole_slot: true });
+            assert!((0..15).all(|x| saved().is_locked((x, 0))));
+
+            dispatch(Action::ClickCell((0, 1)));
+            dispatch(Action::EnterLetter('d'));
+            dispatch(Action::ClearUnlocked);
+            assert_eq!(row(store, 0, 4), "cat ");
+            assert_eq!(row(store, 1, 2), "  ");
+
+            // Locked cells and their symmetric partners stay white.
+            dispatch(Action::ClickCell((2, 0)));
+            dispatch(Action::ToggleBlack);
+            assert!(store.crossword.cell_exists()((2, 0)));
+
+            // Fills only write into the locked cells that are empty.
+            dispatch(Action::ClickCell((0, 0)));
+            dispatch(Action::FillSlot("DOGS".to_owned()));
+            assert_eq!(row(store, 0, 5), "catS ");
+
+            dispatch(Action::Undo);
+            dispatch(Action::Undo);
+            assert_eq!(row(store, 1, 2), "d ");
+            dispatch(Action::ClickCell((1, 0)));
+            dispatch(Action::ToggleLock { whole_slot: false });
+            assert!(!saved().is_locked((1, 0)));
+            assert!(saved().is_locked((0, 0)));
+        });
+    }
+
+    #[test]
+    fn erase_steps_back_over_empty_cells() {
+        with_store(|store, dispatch| {
+            dispatch(Action::ClickCell((0, 0)));
+            dispatch(Action::EnterLetter('a'));
+            dispatch(Action::EnterLetter('b'));
+            dispatch(Action::Erase);
+            assert_eq!(row(store, 0, 3), "a  ");
+            assert_eq!(store.selection.caret_cell()(), Some((1, 0)));
+        });
+    }
+
+    #[test]
+    fn toggle_black_splits_words_and_can_be_undone() {
+        with_store(|store, dispatch| {
+            // Across and down.
+            let word_count = || {
+                store.crossword.answers.with(|a| {
+                    a.answers
+                        .iter()
+                        .map(|h| h.across.is_some() as usize + h.down.is_some() as usize)
+                        .sum::<usize>()
+                })
+            };
+            dispatch(Action::ClickCell((7, 0)));
+            dispatch(Action::ToggleBlack);
+            assert!(!store.crossword.cell_exists()((7, 0)));
+            assert!(!store.crossword.cell_exists()((7, 14)));
+            assert_eq!(store.selection.active_slot.get(), None);
+            assert_eq!(word_count(), DEFAULT_WIDTH + DEFAULT_HEIGHT + 2);
+
+            dispatch(Action::Undo);
+            assert!(store.crossword.cell_exists()((7, 0)));
+            assert!(store.crossword.cell_exists()((7, 14)));
+            assert_eq!(word_count(), DEFAULT_WIDTH + DEFAULT_HEIGHT);
+        });
+    }
+
+    #[test]
+    fn toggles_follow_symmetry_and_asymmetry_is_reported() {
+        with_store(|store, dispatch| {
+            let violations = store.crossword.symmetry_violations();
+
+            dispatch(Action::SetSymmetry(Symmetry::Quarter));
+            dispatch(Action::ClickCell((1, 0)));
+            dispatch(Action::ToggleBlack);
+            for cell in [(1, 0), (14, 1), (13, 14), (0, 13)] {
+                assert!(!store.crossword.cell_exists()(cell), "{:?}", cell);
+            }
+            assert!(violations().is_empty());
+
+            dispatch(Action::SetSymmetry(Symmetry::MirrorLeftRight));
+            assert_eq!(violations(), [(13, 0), (0, 1), (14, 13), (1, 14)]);
+        });
+    }
+
+    #[test]
+    fn validity_follows_black_squares_and_config() {
+        with_store(|store, dispatch| {
+            let validity = move || store.validity.get();
+            assert_eq!(validity(), Validity::default());
+
+            dispatch(Action::ClickCell((1, 0)));
+            dispatch(Action::ToggleBlack);
+            assert_eq!(validity().unchecked, [(0, 0), (14, 14)]);
+
+            dispatch(Action::Undo);
+            dispatch(Action::ClickCell((3, 0)));
+            dispatch(Action::ToggleBlack);
+            assert!(validity().is_valid());
+
+            dispatch(Action::SetGeneratorConfig(GeneratorConfig {
+                min_word_len: 4,
+                ..GeneratorConfig::default()
+            }));
+            assert_eq!(validity().short, [(0, 0, 3, true), (12, 14, 3, true)]);
+            assert!(validity().flags((2, 0)));
+        });
+    }
+
+    #[test]
+    fn set_clue_is_undoable() {
+        with_store(|store, dispatch| {
+            let clue = || {
+                store.crossword.answers.with(|a| {
+                    a.get((0, 0))
+                        .and_then(|h| h.word(true).as_ref())
+                        .map(|w| w.clue.get())
+                })
+            };
+            dispatch(Action::SetClue {
+                head: (0, 0),
+                is_across: true,
+                clue: "Feline".to_owned(),
+            });
+            assert_eq!(clue().as_deref(), Some("Feline"));
+
+            dispatch(Action::Undo);
+            assert_eq!(clue().as_deref(), Some("Enter a clue"));
+        });
+    }
+
+    #[test]
+    fn fill_slot_is_one_undo_step() {
+        with_store(|store, dispatch| {
+            dispatch(Action::ClickCell((0, 0)));
+            dispatch(Action::EnterLetter('q'));
+            dispatch(Action::ClickCell((0, 1)));
+            dispatch(Action::ClickCell((0, 0)));
+            dispatch(Action::FillSlot("CROSSWORD".to_owned()));
+            assert_eq!(row(store, 0, 10), "CROSSWORD ");
+
+            dispatch(Action::Undo);
+            assert_eq!(row(store, 0, 10), "q         ");
+        });
+    }
+
+    #[test]
+    fn autofill_reports_the_unfillable_slot() {
+        with_store(|store, dispatch| {
+            dispatch(Action::Autofill);
+            assert!(store.status.get().is_some());
+            assert!(store.selection.active_slot.get().is_some());
+            assert!(!store.crossword.history.with(|h| h.can_undo()));
+        });
+    }
+
+    #[test]
+    fn generator_uses_configured_word_lengths() {
+        with_store(|store, dispatch| {
+            dispatch(Action::SetGeneratorConfig(GeneratorConfig {
+                avg_word_len: 0.0..100.0,
+                word_count: 0..1000,
+                black_ratio: 0.0..1.0,
+                min_word_len: 4,
+                max_word_len: 7,
+                max_iters: 50,
+                ..GeneratorConfig::default()
+            }));
+            dispatch(Action::RunGenerator { seed: Some(1) });
+
+            let runs = store.crossword.grid.with(|grid| find_runs(grid));
+            assert!(
+                runs.iter().all(|run| (4..=7).contains(&run.2)),
+                "{:?}",
+                runs
+            );
+        });
+    }
+
+    #[test]
+    fn presets_resize_the_grid() {
+        with_store(|store, dispatch| {
+            let size = move || store.crossword.to_saved().size();
+
+            dispatch(Action::ChoosePreset(Preset::Sunday));
+            assert_eq!(size(), (21, 21));
+            assert_eq!(store.generator.get_untracked(), Preset::Sunday.config());
+
+            dispatch(Action::ChoosePreset(Preset::Mini));
+            assert_eq!(size(), (5, 5));
+            dispatch(Action::Undo);
+            assert_eq!(size(), (21, 21));
+        });
+    }
+
+    #[test]
+    fn job_outcomes_are_dropped_after_edits() {
+        let runtime = create_runtime();
+        run_scope(runtime, |cx| {
+            let dictionary = Dictionary::from_list(cx, WordList::parse(DEFAULT_LIST));
+            let grid = Grid::new(cx, DEFAULT_WIDTH, DEFAULT_HEIGHT);
+            let store = Store::new(cx, Crossword::from_grid(cx, grid), dictionary);
+            let dispatch = store.dispatch(cx);
+
+            let mut filled = store.crossword.to_saved();
+            filled.cells[1] = Some('x');
+            store.job_start.set_value(Some(store.crossword.to_saved()));
+            dispatch(Action::ClickCell((0, 0)));
+            dispatch(Action::EnterLetter('a'));
+
+            store.finish_job(cx, Outcome::Fill(filled.clone()));
+            assert_eq!(row(store, 0, 2), "a ");
+            assert!(store.status.get_untracked().is_some());
+
+            store.job_start.set_value(Some(store.crossword.to_saved()));
+            store.finish_job(cx, Outcome::Fill(filled));
+            assert_eq!(row(store, 0, 2), " x");
+        });
+        runtime.dispose();
+    }
+
+    #[test]
+    fn picking_a_candidate_applies_it_and_is_undoable() {
+        with_store(|store, dispatch| {
+            dispatch(Action::SetGeneratorConfig(GeneratorConfig {
+                max_iters: 200,
+                candidates: 3,
+                ..GeneratorConfig::default()
+            }));
+            dispatch(Action::RunGenerator { seed: Some(2) });
+
+            let candidates = store.candidates.get();
+            assert!(candidates.len() > 1);
+            let blacks = || {
+                store
+                    .crossword
+                    .grid
+                    .with(|grid| grid.cells.iter().map(Option::is_none).collect::<Vec<_>>())
+            };
+            assert_eq!(blacks(), candidates[0].cells);
+
+            dispatch(Action::PickCandidate(1));
+            assert_eq!(blacks(), candidates[1].cells);
+
+            dispatch(Action::Undo);
+            assert_eq!(blacks(), candidates[0].cells);
+        });
+    }
+
+    #[test]
+    fn applying_a_template_keeps_letters_and_is_undoable() {
+        with_store(|store, dispatch| {
+            dispatch(Action::ClickCell((1, 1)));
+            dispatch(Action::EnterLetter('a'));
+
+            let template = Template::from_rows("Test", &["#....", ".....", ".....", "....."]);
+            dispatch(Action::ApplyTemplate(template));
+            let saved = store.crossword.to_saved();
+            assert_eq!(saved.size(), (5, 4));
+            assert_eq!(saved.cell((0, 0)), None);
+            assert_eq!(saved.cell((1, 1)), Some('a'));
+
+            dispatch(Action::Undo);
+            assert_eq!(
+                store.crossword.to_saved().size(),
+                (DEFAULT_WIDTH, DEFAULT_HEIGHT)
+            );
+        });
+    }
+
+    #[test]
+    fn resizing_keeps_letters_within_limits() {
+        with_store(|store, dispatch| {
+            dispatch(Action::ClickCell((0, 0)));
+            dispatch(Action::EnterLetter('a'));
+            let size = move || store.crossword.to_saved().size();
+
+            dispatch(Action::Resize {
+                edge: Edge::Left,
+                grow: true,
+            });
+            assert_eq!(size(), (DEFAULT_WIDTH + 1, DEFAULT_HEIGHT));
+            assert_eq!(store.crossword.to_saved().cell((1, 0)), Some('a'));
+
+            for _ in 0..MAX_SIZE {
+                dispatch(Action::Resize {
+                    edge: Edge::Bottom,
+                    grow: true,
+                });
+            }
+            assert_eq!(size(), (DEFAULT_WIDTH + 1, MAX_SIZE));
+
+            dispatch(Action::Undo);
+            assert_eq!(size(), (DEFAULT_WIDTH + 1, MAX_SIZE - 1));
+        });
+    }
+
+    #[test]
+    fn crossings_cover_empty_cells() {
+        with_store(|store, dispatch| {
+            dispatch(Action::ClickCell((0, 1)));
+            dispatch(Action::EnterLetter('a'));
+            dispatch(Action::ClickCell((0, 0)));
+
+            let slot = store.selection.active_slot.get().unwrap();
+            let crossings = store.crossword.crossings()(slot);
+            assert_eq!(crossings.len(), DEFAULT_WIDTH);
+            assert_eq!(crossings[0].offset, 0);
+            assert!(crossings[0].pattern.starts_with(" A"));
+        });
+    }
+
+    #[test]
+    fn clue_rows_follow_grid_numbering() {
+        with_store(|store, dispatch| {
+            dispatch(Action::ClickCell((2, 0)));
+            dispatch(Action::ToggleBlack);
+            dispatch(Action::SelectWord {
+                head: (0, 0),
+                is_across: true,
+            });
+            dispatch(Action::EnterLetter('o'));
+            dispatch(Action::EnterLetter('x'));
+
+            let across = store.crossword.clue_rows()(true);
+            assert_eq!(across[0].number, 1);
+            assert_eq!(across[0].pattern(), "OX");
+            assert_eq!(across[1].head, (3, 0));
+            assert_eq!(
+                store.crossword.slot_pattern()(store.selection.active_slot.get().unwrap()),
+                "OX"
+            );
+            assert_eq!(
+                Some(across[1].number),
+                store.crossword.answer_id_at()((3, 0))
+            );
+        });
+    }
 }
@@ -0,0 +1,398 @@
+use leptos::{leptos_dom::console_warn, *};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use wasm_bindgen::JsValue;
+use web_sys::Event;
+
+use crate::{
+    files, ipuz, puz,
+    storage::{self, SavedCrossword},
+    DEFAULT_HEIGHT, DEFAULT_WIDTH, MAX_SIZE, MIN_SIZE,
+};
+
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Eq, Debug)]
+pub struct Metadata {
+    pub title: String,
+    #[serde(default)]
+    pub author: String,
+    #[serde(default)]
+    pub copyright: String,
+    #[serde(default)]
+    pub notes: String,
+    // Only ipuz files carry the fields below.
+    #[serde(default)]
+    pub publisher: String,
+    #[serde(default)]
+    pub editor: String,
+    #[serde(default)]
+    pub date: String,
+    #[serde(default)]
+    pub origin: String,
+    #[serde(default)]
+    pub intro: String,
+}
+
+/// A puzzle read from another format, along with anything that couldn't be
+/// carried over.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Imported {
+    pub saved: SavedCrossword,
+    pub metadata: Metadata,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct PuzzleMeta {
+    pub id: Uuid,
+    #[serde(flatten)]
+    pub metadata: Metadata,
+    width: usize,
+    height: usize,
+    filled: usize,
+    white: usize,
+    modified: f64,
+}
+
+impl PuzzleMeta {
+    pub fn new(id: Uuid, metadata: Metadata, saved: &SavedCrossword) -> Self {
+        let mut meta = Self {
+            id,
+            metadata,
+            width: 0,
+            height: 0,
+            filled: 0,
+            white: 0,
+            modified: 0.0,
+        };
+        meta.touch(saved);
+        meta
+    }
+
+    fn touch(&mut self, saved: &SavedCrossword) {
+        (self.width, self.height) = saved.size();
+        (self.filled, self.white) = saved.fill();
+        self.modified = js_sys::Date::now();
+    }
+
+    fn fill_percentage(&self) -> usize {
+        (self.filled * 100).checked_div(self.white).unwrap_or(0)
+    }
+
+    fn modified_display(&self) -> String {
+        js_sys::Date::new(&JsValue::from_f64(self.modified))
+            .to_locale_string("default", &JsValue::UNDEFINED)
+            .into()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Library {
+    puzzles: RwSignal<Vec<PuzzleMeta>>,
+    pub open: RwSignal<Option<Uuid>>,
+    messages: RwSignal<Vec<String>>,
+}
+
+impl Library {
+    pub fn new(cx: Scope) -> Self {
+        let mut puzzles = storage::load_library();
+        puzzles.sort_by(|a, b| b.modified.total_cmp(&a.modified));
+        let open = puzzles.first().map(|p| p.id);
+
+        let library = Self {
+            puzzles: create_rw_signal(cx, puzzles),
+            open: create_rw_signal(cx, open),
+            messages: create_rw_signal(cx, Vec::new()),
+        };
+
+        if open.is_none() {
+            library.create()(DEFAULT_WIDTH, DEFAULT_HEIGHT);
+        }
+
+        let puzzles = library.puzzles;
+        create_effect(cx, move |_| puzzles.with(|p| storage::save_library(p)));
+
+        library
+    }
+
+    fn next_title(&self) -> String {
+        let count = self.puzzles.with(|p| p.len());
+        format!("Untitled {}", count + 1)
+    }
+
+    fn add(&self, metadata: Metadata, saved: &SavedCrossword) {
+        let id = Uuid::new_v4();
+        storage::save(id, saved);
+        let meta = PuzzleMeta::new(id, metadata, saved);
+        self.puzzles.update(|p| p.insert(0, meta));
+        self.open.set(Some(id));
+    }
+
+    pub fn create(&self) -> impl Fn(usize, usize) + Copy {
+        let this = *self;
+        move |width, height| {
+            let metadata = Metadata {
+                title: this.next_title(),
+                ..Metadata::default()
+            };
+            this.add(metadata, &SavedCrossword::with_size(width, height));
+        }
+    }
+
+    pub fn import(&self) -> impl Fn(&str, &[u8]) + Copy {
+        let this = *self;
+        move |filename, bytes| {
+            let imported = if filename.to_lowercase().ends_with(".ipuz") {
+                ipuz::import(bytes).map_err(|e| e.to_string())
+            } else {
+                puz::import(bytes).map_err(|e| e.to_string())
+            };
+
+            match imported {
+                Ok(Imported {
+                    saved,
+                    mut metadata,
+                    warnings,
+                }) => {
+                    if metadata.title.is_empty() {
+                        metadata.title = filename.to_owned();
+                    }
+                    this.add(metadata, &saved);
+                    let messages = warnings
+                        .into_iter()
+                        .map(|w| format!("{}: {}", filename, w))
+                        .collect();
+                    this.messages.set(messages);
+                }
+                Err(e) => {
+                    console_warn(&format!("failed to import {}: {}", filename, e));
+                    this.messages
+                        .set(vec![format!("Couldn't import {}: {}", filename, e)]);
+                }
+            }
+        }
+    }
+
+    pub fn duplicate(&self) -> impl Fn(Uuid) + Copy {
+        let puzzles = self.puzzles;
+        move |id| {
+            let Some(saved) = storage::load(id) else {
+                return;
+            };
+            let Some(mut metadata) =
+                puzzles.with(|p| p.iter().find(|m| m.id == id).map(|m| m.metadata.clone()))
+            else {
+                return;
+            };
+            metadata.title = format!("{} (copy)", metadata.title);
+
+            let copy_id = Uuid::new_v4();
+            storage::save(copy_id, &saved);
+            let meta = PuzzleMeta::new(copy_id, metadata, &saved);
+            puzzles.update(|p| p.insert(0, meta));
+        }
+    }
+
+    pub fn delete(&self) -> impl Fn(Uuid) + Copy {
+        let puzzles = self.puzzles;
+        let open = self.open;
+        move |id| {
+            storage::remove(id);
+            puzzles.update(|p| p.retain(|m| m.id != id));
+            if open.get_untracked() == Some(id) {
+                open.set(puzzles.with_untracked(|p| p.first().map(|m| m.id)));
+            }
+        }
+    }
+
+    pub fn rename(&self) -> impl Fn(Uuid, String) + Copy {
+        let this = *self;
+        move |id, title| this.update_metadata(id, |m| m.title = title)
+    }
+
+    pub fn update_metadata(&self, id: Uuid, f: impl FnOnce(&mut Metadata)) {
+        self.puzzles.update(|p| {
+            if let Some(meta) = p.iter_mut().find(|m| m.id == id) {
+                f(&mut meta.metadata);
+            }
+        });
+    }
+
+    pub fn metadata(&self, id: Uuid) -> Option<Metadata> {
+        self.puzzles
+            .with(|p| p.iter().find(|m| m.id == id).map(|m| m.metadata.clone()))
+    }
+
+    pub fn touch(&self, id: Uuid, saved: &SavedCrossword) {
+        self.puzzles.update(|p| {
+            if let Some(meta) = p.iter_mut().find(|m| m.id == id) {
+                meta.touch(saved);
+            }
+        });
+    }
+}
+
+#[component]
+pub fn PuzzleList(cx: Scope) -> impl IntoView {
+    let library = use_context::<Library>(cx).expect("Parent did not provide library");
+    let puzzles = library.puzzles;
+    let open = library.open;
+    let create = library.create();
+    let duplicate = library.duplicate();
+    let delete = library.delete();
+    let rename = library.rename();
+    let import = library.import();
+    let messages = library.messages;
+    let width = create_rw_signal(cx, DEFAULT_WIDTH);
+    let height = create_rw_signal(cx, DEFAULT_HEIGHT);
+
+    let size_input = move |label: &'static str, size: RwSignal<usize>| {
+        let on_change = move |ev| {
+            if let Ok(value) = event_target_value(&ev).parse::<usize>() {
+                size.set(value.clamp(MIN_SIZE, MAX_SIZE));
+            }
+        };
+        view! { cx,
+            <input
+                type="number"
+                class="size"
+                title=label
+                min=MIN_SIZE
+                max=MAX_SIZE
+                prop:value=move || size.get().to_string()
+                on:change=on_change
+            />
+        }
+    };
+
+    let pick_file = move |ev: Event| {
+        spawn_local(async move {
+            if let Some((filename, bytes)) = files::read_picked_file(ev).await {
+                import(&filename, &bytes);
+            }
+        })
+    };
+
+    view! { cx,
+        <div class="library">
+            <div class="toolbar">
+                {size_input("Width", width)}
+                "×"
+                {size_input("Height", height)}
+                <button on:click=move |_| create(width.get(), height.get())>"New puzzle"</button>
+                <label class="button">
+                    "Import"
+                    <input type="file" accept=".puz,.ipuz" on:change=pick_file/>
+                </label>
+            </div>
+            <ul class="messages">
+                {move || {
+                    messages
+                        .get()
+                        .into_iter()
+                        .map(|m| view! { cx, <li>{m}</li> })
+                        .collect::<Vec<_>>()
+                }}
+            </ul>
+            <table>
+                <thead>
+                    <tr>
+                        <th>"Title"</th>
+                        <th>"Size"</th>
+                        <th>"Filled"</th>
+                        <th>"Modified"</th>
+                        <th></th>
+                    </tr>
+                </thead>
+                <tbody>
+                    <For
+                        each=puzzles
+                        key=|meta| (meta.id, meta.modified.to_bits(), meta.metadata.title.clone())
+                        view=move |cx, meta: PuzzleMeta| {
+                            let id = meta.id;
+                            let is_open = move || open() == Some(id);
+                            let on_rename = move |ev| rename(id, event_target_value(&ev));
+                            view! { cx,
+                                <tr class:open=is_open>
+                                    <td>
+                                        <input type="text" value=meta.metadata.title.clone() on:change=on_rename/>
+                                    </td>
+                                    <td>{format!("{}×{}", meta.width, meta.height)}</td>
+                                    <td>{format!("{}%", meta.fill_percentage())}</td>
+                                    <td>{meta.modified_display()}</td>
+                                    <td>
+                                        <button on:click=move |_| open.set(Some(id))>"Open"</button>
+                                        <button on:click=move |_| duplicate(id)>"Duplicate"</button>
+                                        <button on:click=move |_| delete(id)>"Delete"</button>
+                                    </td>
+                                </tr>
+                            }
+                        }
+                    />
+                </tbody>
+            </table>
+        </div>
+    }
+}
+
+#[component]
+pub fn PuzzleDetails<S>(cx: Scope, id: Uuid, saved: S) -> impl IntoView
+where
+    S: Fn() -> SavedCrossword + 'static + Copy,
+{
+    let library = use_context::<Library>(cx).expect("Parent did not provide library");
+    let metadata = move || library.metadata(id).unwrap_or_default();
+
+    let field =
+        move |label: &'static str, get: fn(&Metadata) -> String, set: fn(&mut Metadata, String)| {
+            let on_change = move |ev| {
+                let value = event_target_value(&ev);
+                library.update_metadata(id, |m| set(m, value));
+            };
+            view! { cx,
+                <label>
+                    {label}
+                    <input type="text" prop:value=move || get(&metadata()) on:change=on_change/>
+                </label>
+            }
+        };
+
+    let export_puz = move |_| {
+        let metadata = metadata();
+        match puz::export(&saved(), &metadata) {
+            Ok(bytes) => files::download(
+                &format!("{}.puz", metadata.title),
+                "application/x-crossword",
+                &bytes,
+            ),
+            Err(e) => library
+                .messages
+                .set(vec![format!("Couldn't export .puz: {}", e)]),
+        }
+    };
+
+    let export_ipuz = move |_| {
+        let metadata = metadata();
+        let json = ipuz::export(&saved(), &metadata);
+        files::download(
+            &format!("{}.ipuz", metadata.title),
+            "application/json",
+            json.as_bytes(),
+        );
+    };
+
+    view! { cx,
+        <div class="details">
+            {field("Title", |m| m.title.clone(), |m, v| m.title = v)}
+            {field("Author", |m| m.author.clone(), |m, v| m.author = v)}
+            {field("Copyright", |m| m.copyright.clone(), |m, v| m.copyright = v)}
+            {field("Notes", |m| m.notes.clone(), |m, v| m.notes = v)}
+            {field("Publisher", |m| m.publisher.clone(), |m, v| m.publisher = v)}
+            {field("Editor", |m| m.editor.clone(), |m, v| m.editor = v)}
+            {field("Date", |m| m.date.clone(), |m, v| m.date = v)}
+            {field("Origin", |m| m.origin.clone(), |m, v| m.origin = v)}
+            {field("Intro", |m| m.intro.clone(), |m, v| m.intro = v)}
+            <button on:click=export_puz>"Export .puz"</button>
+            <button on:click=export_ipuz>"Export .ipuz"</button>
+        </div>
+    }
+}
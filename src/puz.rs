@@ -0,0 +1,567 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{
+    library::{Imported, Metadata},
+    storage::SavedCrossword,
+};
+
+const MAGIC: &[u8] = b"ACROSS&DOWN\0";
+const MASK: &[u8; 8] = b"ICHEATED";
+const VERSION: &[u8; 4] = b"1.3\0";
+const HEADER_LEN: usize = 0x34;
+const BLACK: u8 = b'.';
+const EMPTY: u8 = b'-';
+const CIRCLED: u8 = 0x80;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PuzError {
+    NotAPuzFile,
+    Truncated,
+    EmptyGrid,
+    /// A side is longer than the byte the format stores it in.
+    TooLarge,
+    /// More distinct rebus entries than the byte keys of GRBS can tell apart.
+    TooManyRebuses,
+    /// The format has no bars, so a barred grid's words can't be kept.
+    Barred,
+}
+
+impl Display for PuzError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PuzError::NotAPuzFile => write!(f, "not an Across Lite file"),
+            PuzError::Truncated => write!(f, "file ends unexpectedly"),
+            PuzError::EmptyGrid => write!(f, "grid has no cells"),
+            PuzError::TooLarge => write!(f, "grid sides can't be longer than 255 cells"),
+            PuzError::TooManyRebuses => {
+                write!(f, "grid can't have more than 255 different rebuses")
+            }
+            PuzError::Barred => write!(f, "Across Lite files can't have bars between cells"),
+        }
+    }
+}
+
+fn checksum(data: &[u8], mut sum: u16) -> u16 {
+    for &byte in data {
+        sum = if sum & 1 == 1 {
+            (sum >> 1) + 0x8000
+        } else {
+            sum >> 1
+        };
+        sum = sum.wrapping_add(byte as u16);
+    }
+    sum
+}
+
+struct Strings {
+    title: Vec<u8>,
+    author: Vec<u8>,
+    copyright: Vec<u8>,
+    clues: Vec<Vec<u8>>,
+    notes: Vec<u8>,
+}
+
+impl Strings {
+    fn checksum(&self, mut sum: u16) -> u16 {
+        for s in [&self.title, &self.author, &self.copyright] {
+            if !s.is_empty() {
+                sum = checksum(s, sum);
+                sum = checksum(&[0], sum);
+            }
+        }
+        for clue in self.clues.iter() {
+            sum = checksum(clue, sum);
+        }
+        if !self.notes.is_empty() {
+            sum = checksum(&self.notes, sum);
+            sum = checksum(&[0], sum);
+        }
+        sum
+    }
+}
+
+struct Checksums {
+    overall: u16,
+    cib: u16,
+    masked: [u8; 8],
+}
+
+impl Checksums {
+    fn new(cib: &[u8], solution: &[u8], state: &[u8], strings: &Strings) -> Self {
+        let cib = checksum(cib, 0);
+        let overall = strings.checksum(checksum(state, checksum(solution, cib)));
+        let parts = [
+            cib,
+            checksum(solution, 0),
+            checksum(state, 0),
+            strings.checksum(0),
+        ];
+
+        let mut masked = [0; 8];
+        for (i, part) in parts.into_iter().enumerate() {
+            masked[i] = MASK[i] ^ (part & 0xFF) as u8;
+            masked[i + 4] = MASK[i + 4] ^ (part >> 8) as u8;
+        }
+
+        Self {
+            overall,
+            cib,
+            masked,
+        }
+    }
+}
+
+// .puz text is ISO-8859-1, which maps byte-for-byte onto the first 256 code
+// points.
+fn encode(s: &str) -> Vec<u8> {
+    s.chars()
+        .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+        .collect()
+}
+
+fn decode(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+pub fn export(saved: &SavedCrossword, metadata: &Metadata) -> Result<Vec<u8>, PuzError> {
+    if saved.is_barred() {
+        return Err(PuzError::Barred);
+    }
+    let (width, height) = saved.size();
+    let (Ok(width_byte), Ok(height_byte)) = (u8::try_from(width), u8::try_from(height)) else {
+        return Err(PuzError::TooLarge);
+    };
+
+    let solution = saved
+        .cells
+        .iter()
+        .map(|cell| match cell {
+            None => BLACK,
+            // `.` and `-` mean black and empty, so other symbols are left out
+            // too rather than risk corrupting the grid.
+            Some(letter) if !letter.is_alphanumeric() => EMPTY,
+            Some(letter) => encode(&letter.to_uppercase().to_string())[0],
+        })
+        .collect::<Vec<_>>();
+    let state = saved
+        .cells
+        .iter()
+        .map(|cell| if cell.is_some() { EMPTY } else { BLACK })
+        .collect::<Vec<_>>();
+
+    let entries = saved.entries();
+    let strings = Strings {
+        title: encode(&metadata.title),
+        author: encode(&metadata.author),
+        copyright: encode(&metadata.copyright),
+        clues: entries
+            .iter()
+            .map(|e| encode(saved.clue(e.head, e.is_across).unwrap_or_default()))
+            .collect(),
+        notes: encode(&metadata.notes),
+    };
+
+    let mut cib = Vec::with_capacity(8);
+    cib.push(width_byte);
+    cib.push(height_byte);
+    cib.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    cib.extend_from_slice(&1u16.to_le_bytes());
+    cib.extend_from_slice(&0u16.to_le_bytes());
+
+    let checksums = Checksums::new(&cib, &solution, &state, &strings);
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&checksums.overall.to_le_bytes());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&checksums.cib.to_le_bytes());
+    out.extend_from_slice(&checksums.masked);
+    out.extend_from_slice(VERSION);
+    out.resize(0x2C, 0);
+    out.extend_from_slice(&cib);
+    out.extend_from_slice(&solution);
+    out.extend_from_slice(&state);
+    for s in [&strings.title, &strings.author, &strings.copyright] {
+        out.extend_from_slice(s);
+        out.push(0);
+    }
+    for clue in strings.clues.iter() {
+        out.extend_from_slice(clue);
+        out.push(0);
+    }
+    out.extend_from_slice(&strings.notes);
+    out.push(0);
+
+    // Rebus cells point into a table of their letters, keyed from 1 in GRBS
+    // and from 0 in RTBL.
+    let mut table = Vec::<String>::new();
+    let grbs = (0..saved.cells.len())
+        .map(|i| {
+            let Some(letters) = saved
+                .letters((i % width, i / width))
+                .filter(|letters| letters.chars().count() > 1)
+            else {
+                return Some(0);
+            };
+            let letters = letters.to_uppercase();
+            let key = table.iter().position(|l| *l == letters).unwrap_or_else(|| {
+                table.push(letters);
+                table.len() - 1
+            });
+            u8::try_from(key + 1).ok()
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or(PuzError::TooManyRebuses)?;
+    if !table.is_empty() {
+        let rtbl = table
+            .iter()
+            .enumerate()
+            .map(|(key, letters)| format!("{:>2}:{};", key, letters))
+            .collect::<String>();
+        section(&mut out, b"GRBS", &grbs);
+        section(&mut out, b"RTBL", &encode(&rtbl));
+    }
+
+    // GEXT only has a flag for circles, so shading and bold borders are
+    // left out.
+    let gext = (0..saved.cells.len())
+        .map(|i| {
+            if saved.style((i % width, i / width)).circle {
+                CIRCLED
+            } else {
+                0
+            }
+        })
+        .collect::<Vec<_>>();
+    if gext.contains(&CIRCLED) {
+        section(&mut out, b"GEXT", &gext);
+    }
+
+    Ok(out)
+}
+
+fn section(out: &mut Vec<u8>, name: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(name);
+    out.extend_from_slice(&(data.len() as u16).to_le_bytes());
+    out.extend_from_slice(&checksum(data, 0).to_le_bytes());
+    out.extend_from_slice(data);
+    out.push(0);
+}
+
+/// Parses an RTBL section, which lists each rebus as ` 0:HEART;`.
+fn rebus_table(data: &[u8]) -> HashMap<u8, String> {
+    decode(data)
+        .split(';')
+        .filter_map(|entry| {
+            let (key, letters) = entry.split_once(':')?;
+            Some((key.trim().parse().ok()?, letters.to_owned()))
+        })
+        .collect()
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], PuzError> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + n)
+            .ok_or(PuzError::Truncated)?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, PuzError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn string(&mut self) -> Result<&'a [u8], PuzError> {
+        let rest = self.bytes.get(self.pos..).ok_or(PuzError::Truncated)?;
+        let len = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(PuzError::Truncated)?;
+        self.pos += len + 1;
+        Ok(&rest[..len])
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len().saturating_sub(self.pos)
+    }
+}
+
+pub fn import(bytes: &[u8]) -> Result<Imported, PuzError> {
+    // Some files carry junk before the header, so find it by its magic string
+    // rather than assuming it starts at byte zero.
+    let start = bytes
+        .windows(MAGIC.len())
+        .position(|w| w == MAGIC)
+        .and_then(|p| p.checked_sub(2))
+        .ok_or(PuzError::NotAPuzFile)?;
+
+    let mut reader = Reader {
+        bytes: &bytes[start..],
+        pos: 0,
+    };
+    let header = reader.take(HEADER_LEN)?;
+    let overall = u16::from_le_bytes([header[0], header[1]]);
+    let cib = &header[0x2C..0x34];
+    let width = cib[0] as usize;
+    let height = cib[1] as usize;
+    let clue_count = u16::from_le_bytes([cib[2], cib[3]]) as usize;
+    let scrambled = u16::from_le_bytes([cib[6], cib[7]]) != 0;
+
+    if width == 0 || height == 0 {
+        return Err(PuzError::EmptyGrid);
+    }
+
+    let solution = reader.take(width * height)?;
+    let state = reader.take(width * height)?;
+    let title = reader.string()?;
+    let author = reader.string()?;
+    let copyright = reader.string()?;
+    let clues = (0..clue_count)
+        .map(|_| reader.string())
+        .collect::<Result<Vec<_>, _>>()?;
+    // Notes only became part of the format in version 1.3.
+    let notes = reader.string().unwrap_or_default();
+
+    let mut warnings = Vec::new();
+
+    let strings = Strings {
+        title: title.to_vec(),
+        author: author.to_vec(),
+        copyright: copyright.to_vec(),
+        clues: clues.iter().map(|c| c.to_vec()).collect(),
+        notes: notes.to_vec(),
+    };
+    let checksums = Checksums::new(cib, solution, state, &strings);
+    if checksums.overall != overall || checksums.masked[..] != header[0x10..0x18] {
+        warnings.push("checksums don't match, the file may be damaged".to_owned());
+    }
+
+    let mut grbs = None;
+    let mut rtbl = HashMap::new();
+    let mut gext = None;
+    while reader.remaining() >= 8 {
+        let name = reader.take(4)?;
+        let len = reader.u16()? as usize;
+        let section_checksum = reader.u16()?;
+        let data = reader.take(len)?;
+        reader.take(1)?;
+
+        if checksum(data, 0) != section_checksum {
+            warnings.push(format!(
+                "{} section checksum doesn't match",
+                String::from_utf8_lossy(name)
+            ));
+        }
+
+        match name {
+            b"GRBS" => grbs = Some(data),
+            b"RTBL" => rtbl = rebus_table(data),
+            b"GEXT" => gext = Some(data),
+            b"LTIM" | b"RUSR" => (),
+            name => warnings.push(format!(
+                "skipped unknown {} section",
+                String::from_utf8_lossy(name)
+            )),
+        }
+    }
+
+    if scrambled {
+        warnings.push("solution is scrambled, so only the grid shape was imported".to_owned());
+    }
+
+    let cells = solution
+        .iter()
+        .map(|&b| match b {
+            BLACK => None,
+            _ if scrambled => Some(' '),
+            b if b.is_ascii_alphanumeric() => Some((b as char).to_ascii_uppercase()),
+            _ => Some(' '),
+        })
+        .collect();
+    let mut saved = SavedCrossword::new(width, cells);
+
+    for (i, &flags) in gext.into_iter().flatten().enumerate() {
+        let cell = (i % width, i / width);
+        if flags & CIRCLED != 0 {
+            let mut style = saved.style(cell);
+            style.circle = true;
+            saved.set_style(cell, style);
+        }
+    }
+
+    if let Some(grbs) = grbs.filter(|_| !scrambled) {
+        let mut missing = 0;
+        for (i, &key) in grbs.iter().enumerate().filter(|(_, &key)| key != 0) {
+            match rtbl.get(&(key - 1)) {
+                Some(letters) => saved.set_letters((i % width, i / width), letters),
+                None => missing += 1,
+            }
+        }
+        if missing > 0 {
+            warnings.push(format!(
+                "{} rebus cells were imported with only their first letter",
+                missing
+            ));
+        }
+    }
+
+    let entries = saved.entries();
+    if entries.len() != clues.len() {
+        warnings.push(format!(
+            "grid has {} entries but the file has {} clues",
+            entries.len(),
+            clues.len()
+        ));
+    }
+    for (entry, clue) in entries.iter().zip(clues.iter()) {
+        saved.set_clue(entry.head, entry.is_across, decode(clue));
+    }
+
+    Ok(Imported {
+        saved,
+        metadata: Metadata {
+            title: decode(title),
+            author: decode(author),
+            copyright: decode(copyright),
+            notes: decode(notes),
+            ..Metadata::default()
+        },
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CellStyle, Edge};
+
+    fn metadata() -> Metadata {
+        Metadata {
+            title: "Tiny".to_owned(),
+            author: "Setter".to_owned(),
+            copyright: "© 2023".to_owned(),
+            ..Metadata::default()
+        }
+    }
+
+    #[test]
+    fn checksum_matches_reference_algorithm() {
+        assert_eq!(checksum(&[], 0), 0);
+        assert_eq!(checksum(&[1], 0), 1);
+        assert_eq!(checksum(&[1, 1], 0), 0x8001);
+        assert_eq!(checksum(b"\x03\x03", 0x1234), 0x8491);
+    }
+
+    #[test]
+    fn export_then_import_round_trips() {
+        let mut saved = SavedCrossword::new(
+            3,
+            vec![
+                Some('C'),
+                Some('A'),
+                Some('T'),
+                Some('A'),
+                None,
+                Some('O'),
+                Some('R'),
+                Some('O'),
+                Some('E'),
+            ],
+        );
+        for (i, entry) in saved.entries().into_iter().enumerate() {
+            saved.set_clue(entry.head, entry.is_across, format!("Clue {}", i));
+        }
+
+        let bytes = export(&saved, &metadata()).unwrap();
+        let imported = import(&bytes).unwrap();
+
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+        assert_eq!(imported.saved, saved);
+        assert_eq!(imported.metadata, metadata());
+    }
+
+    #[test]
+    fn export_leaves_out_symbols() {
+        let mut cells = vec![Some(' '); 9];
+        cells[..3].copy_from_slice(&[Some('.'), Some('-'), Some('a')]);
+        let saved = SavedCrossword::new(3, cells);
+
+        let imported = import(&export(&saved, &metadata()).unwrap()).unwrap();
+        assert_eq!(imported.saved.cells[..3], [Some(' '), Some(' '), Some('A')]);
+    }
+
+    #[test]
+    fn export_refuses_what_the_format_cant_hold() {
+        let saved = SavedCrossword::new(256, vec![Some(' '); 256 * 3]);
+        assert_eq!(export(&saved, &metadata()), Err(PuzError::TooLarge));
+
+        let mut saved = SavedCrossword::new(20, vec![Some(' '); 400]);
+        for i in 0..256 {
+            saved.set_letters((i % 20, i / 20), &format!("R{}", i));
+        }
+        assert_eq!(export(&saved, &metadata()), Err(PuzError::TooManyRebuses));
+
+        saved.set_letters((0, 0), "R1");
+        assert!(export(&saved, &metadata()).is_ok());
+    }
+
+    #[test]
+    fn rebus_cells_round_trip() {
+        let mut saved = SavedCrossword::new(3, vec![Some(' '); 9]);
+        saved.set_letters((0, 0), "HEART");
+        saved.set_letters((2, 2), "HEART");
+        saved.set_letters((1, 1), "ON");
+
+        let bytes = export(&saved, &metadata()).unwrap();
+        let imported = import(&bytes).unwrap();
+
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+        assert_eq!(imported.saved.letters((0, 0)).as_deref(), Some("HEART"));
+        assert_eq!(imported.saved.letters((2, 2)).as_deref(), Some("HEART"));
+        assert_eq!(imported.saved.letters((1, 1)).as_deref(), Some("ON"));
+        assert_eq!(imported.saved.letters((1, 0)).as_deref(), Some(" "));
+    }
+
+    #[test]
+    fn circles_round_trip() {
+        let mut saved = SavedCrossword::new(3, vec![Some(' '); 9]);
+        let circle = CellStyle {
+            circle: true,
+            ..CellStyle::default()
+        };
+        saved.set_style((1, 1), circle.clone());
+        saved.set_style(
+            (2, 2),
+            CellStyle {
+                bold: true,
+                ..CellStyle::default()
+            },
+        );
+
+        let imported = import(&export(&saved, &metadata()).unwrap()).unwrap();
+
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+        assert_eq!(imported.saved.style((1, 1)), circle);
+        assert!(imported.saved.style((2, 2)).is_plain());
+    }
+
+    #[test]
+    fn export_refuses_barred_grids() {
+        let mut saved = SavedCrossword::new(3, vec![Some(' '); 9]);
+        saved.add_bar((0, 0), Edge::Right);
+
+        assert_eq!(export(&saved, &metadata()), Err(PuzError::Barred));
+    }
+
+    #[test]
+    fn import_rejects_other_files() {
+        assert_eq!(import(b"hello").err(), Some(PuzError::NotAPuzFile));
+    }
+}
@@ -0,0 +1,804 @@
+use leptos::{leptos_dom::console_warn, *};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+use web_sys::Storage;
+
+use crate::{
+    find_runs,
+    generate::{GeneratorConfig, Symmetry},
+    library::{Metadata, PuzzleMeta},
+    templates::Template,
+    Bars, BoolMatrix, Cell, CellStyle, Crossword, Edge, Grid, TriBoolMatrix, DEFAULT_HEIGHT,
+    DEFAULT_WIDTH, STORAGE_KEY,
+};
+
+// Bump this whenever `SavedCrossword` changes in a way older data can't be
+// read as, and add an arm to `migrate` that upgrades the previous version.
+// Fields added with `#[serde(default)]` load from older data as they are, so
+// they don't need a bump.
+const SCHEMA_VERSION: u64 = 1;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct SavedCrossword {
+    version: u64,
+    pub width: usize,
+    pub cells: Vec<Option<char>>,
+    clues: Vec<SavedClue>,
+    #[serde(default)]
+    pub symmetry: Symmetry,
+    /// Cells holding more than one letter, which `cells` only has the first
+    /// letter of.
+    #[serde(default)]
+    rebus: Vec<SavedRebus>,
+    /// Cells that are circled, shaded or bold.
+    #[serde(default)]
+    styles: Vec<SavedStyle>,
+    /// Cells with bars on their right or bottom edge.
+    #[serde(default)]
+    bars: Vec<SavedBars>,
+    /// White cells that keep their letters and never turn black.
+    #[serde(default)]
+    locked: Vec<(usize, usize)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+struct SavedRebus {
+    cell: (usize, usize),
+    letters: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+struct SavedStyle {
+    cell: (usize, usize),
+    style: CellStyle,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+struct SavedBars {
+    cell: (usize, usize),
+    bars: Bars,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+struct SavedClue {
+    head: (usize, usize),
+    is_across: bool,
+    clue: String,
+}
+
+/// A numbered entry in the grid, in the usual across-then-down reading order.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Entry {
+    pub number: usize,
+    pub head: (usize, usize),
+    pub len: usize,
+    pub is_across: bool,
+}
+
+impl Entry {
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> {
+        let Entry {
+            head, is_across, ..
+        } = *self;
+        (0..self.len).map(move |i| {
+            if is_across {
+                (head.0 + i, head.1)
+            } else {
+                (head.0, head.1 + i)
+            }
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedWordList {
+    name: String,
+    text: String,
+}
+
+fn local_storage() -> Option<Storage> {
+    window().local_storage().ok().flatten()
+}
+
+fn library_key() -> String {
+    format!("{}:library", STORAGE_KEY)
+}
+
+fn word_list_key() -> String {
+    format!("{}:words", STORAGE_KEY)
+}
+
+fn generator_key() -> String {
+    format!("{}:generator", STORAGE_KEY)
+}
+
+fn templates_key() -> String {
+    format!("{}:templates", STORAGE_KEY)
+}
+
+fn puzzle_key(id: Uuid) -> String {
+    format!("{}:puzzle:{}", STORAGE_KEY, id)
+}
+
+fn read(key: &str) -> Option<String> {
+    local_storage()?.get_item(key).ok().flatten()
+}
+
+fn write<T: Serialize>(key: &str, value: &T) {
+    let Some(storage) = local_storage() else {
+        return;
+    };
+
+    match serde_json::to_string(value) {
+        Ok(json) => {
+            if storage.set_item(key, &json).is_err() {
+                console_warn(&format!("failed to write {} to local storage", key));
+            }
+        }
+        Err(e) => console_warn(&format!("failed to serialize {}: {}", key, e)),
+    }
+}
+
+fn parse_crossword(json: &str) -> Option<SavedCrossword> {
+    match serde_json::from_str(json).and_then(migrate) {
+        Ok(saved) => Some(saved),
+        Err(e) => {
+            console_warn(&format!("discarding saved crossword: {}", e));
+            None
+        }
+    }
+}
+
+pub fn load(id: Uuid) -> Option<SavedCrossword> {
+    parse_crossword(&read(&puzzle_key(id))?)
+}
+
+pub fn save(id: Uuid, saved: &SavedCrossword) {
+    write(&puzzle_key(id), saved);
+}
+
+pub fn remove(id: Uuid) {
+    if let Some(storage) = local_storage() {
+        _ = storage.remove_item(&puzzle_key(id));
+    }
+}
+
+pub fn load_library() -> Vec<PuzzleMeta> {
+    if let Some(json) = read(&library_key()) {
+        return serde_json::from_str(&json).unwrap_or_else(|e| {
+            console_warn(&format!("discarding puzzle library: {}", e));
+            Vec::new()
+        });
+    }
+
+    // Before the library existed a single puzzle was saved directly under
+    // `STORAGE_KEY`, so adopt it as the first entry.
+    let Some(saved) = read(STORAGE_KEY).and_then(|json| parse_crossword(&json)) else {
+        return Vec::new();
+    };
+
+    let metadata = Metadata {
+        title: "Untitled".to_owned(),
+        ..Metadata::default()
+    };
+    let meta = PuzzleMeta::new(Uuid::new_v4(), metadata, &saved);
+    save(meta.id, &saved);
+    save_library(std::slice::from_ref(&meta));
+    if let Some(storage) = local_storage() {
+        _ = storage.remove_item(STORAGE_KEY);
+    }
+
+    vec![meta]
+}
+
+pub fn save_library(puzzles: &[PuzzleMeta]) {
+    write(&library_key(), &puzzles);
+}
+
+pub fn load_generator_config() -> Option<GeneratorConfig> {
+    let json = read(&generator_key())?;
+    serde_json::from_str(&json)
+        .map_err(|e| console_warn(&format!("discarding generator settings: {}", e)))
+        .ok()
+}
+
+pub fn save_generator_config(config: &GeneratorConfig) {
+    write(&generator_key(), config);
+}
+
+pub fn load_templates() -> Vec<Template> {
+    let Some(json) = read(&templates_key()) else {
+        return Vec::new();
+    };
+    serde_json::from_str(&json).unwrap_or_else(|e| {
+        console_warn(&format!("discarding saved templates: {}", e));
+        Vec::new()
+    })
+}
+
+pub fn save_templates(templates: &[Template]) {
+    write(&templates_key(), &templates);
+}
+
+pub fn load_word_list() -> Option<(String, String)> {
+    let json = read(&word_list_key())?;
+    match serde_json::from_str::<SavedWordList>(&json) {
+        Ok(SavedWordList { name, text }) => Some((name, text)),
+        Err(e) => {
+            console_warn(&format!("discarding saved word list: {}", e));
+            None
+        }
+    }
+}
+
+pub fn save_word_list(name: &str, text: &str) {
+    write(
+        &word_list_key(),
+        &SavedWordList {
+            name: name.to_owned(),
+            text: text.to_owned(),
+        },
+    );
+}
+
+pub fn remove_word_list() {
+    if let Some(storage) = local_storage() {
+        _ = storage.remove_item(&word_list_key());
+    }
+}
+
+fn migrate(value: Value) -> Result<SavedCrossword, serde_json::Error> {
+    let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+
+    let saved: SavedCrossword = match version {
+        SCHEMA_VERSION => serde_json::from_value(value)?,
+        version => {
+            return Err(serde::de::Error::custom(format!(
+                "unsupported schema version {}",
+                version
+            )))
+        }
+    };
+
+    if saved.width == 0 || !saved.cells.chunks_exact(saved.width).remainder().is_empty() {
+        return Err(serde::de::Error::custom("cells do not form a grid"));
+    }
+
+    Ok(saved)
+}
+
+impl SavedCrossword {
+    pub fn new(width: usize, cells: Vec<Option<char>>) -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            width,
+            cells,
+            clues: Vec::new(),
+            symmetry: Symmetry::default(),
+            rebus: Vec::new(),
+            styles: Vec::new(),
+            bars: Vec::new(),
+            locked: Vec::new(),
+        }
+    }
+
+    pub fn blank() -> Self {
+        Self::with_size(DEFAULT_WIDTH, DEFAULT_HEIGHT)
+    }
+
+    pub fn with_size(width: usize, height: usize) -> Self {
+        Self::new(width, vec![Some(' '); width * height])
+    }
+
+    pub fn cell(&self, (x, y): (usize, usize)) -> Option<char> {
+        if x >= self.width {
+            return None;
+        }
+        self.cells.get(x + y * self.width).copied().flatten()
+    }
+
+    /// Everything written in the cell at `(x, y)`, which is more than one
+    /// letter for a rebus, or a space if it's empty. `None` for a black cell.
+    pub fn letters(&self, cell: (usize, usize)) -> Option<String> {
+        let letter = self.cell(cell)?;
+        Some(
+            self.rebus
+                .iter()
+                .find(|rebus| rebus.cell == cell)
+                .map_or_else(|| letter.to_string(), |rebus| rebus.letters.clone()),
+        )
+    }
+
+    /// Writes `letters` into a white cell, or a space if it's empty.
+    pub fn set_letters(&mut self, cell: (usize, usize), letters: &str) {
+        let Some(white) = self
+            .cells
+            .get_mut(cell.0 + cell.1 * self.width)
+            .filter(|_| cell.0 < self.width)
+            .and_then(Option::as_mut)
+        else {
+            return;
+        };
+
+        let mut chars = letters.chars();
+        *white = chars.next().unwrap_or(' ');
+        self.rebus.retain(|rebus| rebus.cell != cell);
+        if chars.next().is_some() {
+            self.rebus.push(SavedRebus {
+                cell,
+                letters: letters.to_owned(),
+            });
+        }
+    }
+
+    pub fn style(&self, cell: (usize, usize)) -> CellStyle {
+        self.styles
+            .iter()
+            .find(|style| style.cell == cell)
+            .map(|style| style.style.clone())
+            .unwrap_or_default()
+    }
+
+    /// Styles a white cell, only keeping styles that aren't plain.
+    pub fn set_style(&mut self, cell: (usize, usize), style: CellStyle) {
+        if self.cell(cell).is_none() {
+            return;
+        }
+        self.styles.retain(|saved| saved.cell != cell);
+        if !style.is_plain() {
+            self.styles.push(SavedStyle { cell, style });
+        }
+    }
+
+    pub fn bars(&self, cell: (usize, usize)) -> Bars {
+        self.bars
+            .iter()
+            .find(|bars| bars.cell == cell)
+            .map(|bars| bars.bars)
+            .unwrap_or_default()
+    }
+
+    /// Whether any cell has a bar, which splits words without a black square.
+    pub fn is_barred(&self) -> bool {
+        !self.bars.is_empty()
+    }
+
+    /// Sets the bars of a cell inside the grid, black or white.
+    pub fn set_bars(&mut self, cell: (usize, usize), bars: Bars) {
+        let (width, height) = self.size();
+        if cell.0 >= width || cell.1 >= height {
+            return;
+        }
+        self.bars.retain(|saved| saved.cell != cell);
+        if !bars.is_plain() {
+            self.bars.push(SavedBars { cell, bars });
+        }
+    }
+
+    /// Adds the bar on `edge` of `cell`, which is kept by the cell to its left
+    /// or above for the left and top edges.
+    pub fn add_bar(&mut self, cell: (usize, usize), edge: Edge) {
+        if let Some((owner, right)) = Bars::owner(cell, edge, self.size()) {
+            let mut bars = self.bars(owner);
+            if right {
+                bars.right = true;
+            } else {
+                bars.bottom = true;
+            }
+            self.set_bars(owner, bars);
+        }
+    }
+
+    pub fn is_locked(&self, cell: (usize, usize)) -> bool {
+        self.locked.contains(&cell)
+    }
+
+    /// Locks or unlocks a white cell.
+    pub fn set_locked(&mut self, cell: (usize, usize), locked: bool) {
+        self.locked.retain(|&c| c != cell);
+        if locked && self.cell(cell).is_some() {
+            self.locked.push(cell);
+        }
+    }
+
+    /// Drops rebus letters, styles and locks from cells that are now black or
+    /// outside the grid, and bars from outside it.
+    pub fn prune(&mut self) {
+        let rebus = std::mem::take(&mut self.rebus);
+        self.rebus = rebus
+            .into_iter()
+            .filter(|rebus| self.cell(rebus.cell).is_some())
+            .collect();
+        let styles = std::mem::take(&mut self.styles);
+        self.styles = styles
+            .into_iter()
+            .filter(|style| self.cell(style.cell).is_some())
+            .collect();
+        let locked = std::mem::take(&mut self.locked);
+        self.locked = locked
+            .into_iter()
+            .filter(|&cell| self.cell(cell).is_some())
+            .collect();
+        let (width, height) = self.size();
+        self.bars
+            .retain(|bars| bars.cell.0 < width && bars.cell.1 < height);
+    }
+
+    /// The letters written in `entry`, with a space for each empty cell.
+    pub fn answer(&self, entry: &Entry) -> String {
+        entry
+            .cells()
+            .filter_map(|cell| self.letters(cell))
+            .collect()
+    }
+
+    pub fn entries(&self) -> Vec<Entry> {
+        let mut number = 0;
+        let mut last_head = None;
+
+        find_runs(self)
+            .into_iter()
+            .map(|(x, y, len, is_across)| {
+                if last_head != Some((x, y)) {
+                    number += 1;
+                    last_head = Some((x, y));
+                }
+                Entry {
+                    number,
+                    head: (x, y),
+                    len,
+                    is_across,
+                }
+            })
+            .collect()
+    }
+
+    pub fn clue(&self, head: (usize, usize), is_across: bool) -> Option<&str> {
+        self.clues
+            .iter()
+            .find(|c| c.head == head && c.is_across == is_across)
+            .map(|c| c.clue.as_str())
+    }
+
+    pub fn set_clue(&mut self, head: (usize, usize), is_across: bool, clue: String) {
+        match self
+            .clues
+            .iter_mut()
+            .find(|c| c.head == head && c.is_across == is_across)
+        {
+            Some(saved_clue) => saved_clue.clue = clue,
+            None => self.clues.push(SavedClue {
+                head,
+                is_across,
+                clue,
+            }),
+        }
+    }
+
+    /// Whether `other` has the same cells, letters and markings, whatever its
+    /// clues say.
+    pub fn same_grid(&self, other: &SavedCrossword) -> bool {
+        let without_clues = |saved: &SavedCrossword| SavedCrossword {
+            clues: Vec::new(),
+            ..saved.clone()
+        };
+        without_clues(self) == without_clues(other)
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.cells.len() / self.width)
+    }
+
+    /// Adds or removes rows and columns at the bottom and right edges until the
+    /// grid is `width` by `height`.
+    pub fn resized_to(&self, width: usize, height: usize) -> SavedCrossword {
+        let mut resized = self.clone();
+        while resized.size().0 != width {
+            resized = resized.resized(Edge::Right, resized.size().0 < width);
+        }
+        while resized.size().1 != height {
+            resized = resized.resized(Edge::Bottom, resized.size().1 < height);
+        }
+        resized
+    }
+
+    /// Adds a blank row or column along `edge`, or removes the one there,
+    /// keeping every other letter and clue in place. Clues whose word starts
+    /// in a removed cell are dropped.
+    pub fn resized(&self, edge: Edge, grow: bool) -> SavedCrossword {
+        let (width, height) = self.size();
+        let step = if grow { 1 } else { -1 };
+        // Where the old top left cell ends up, and the new size.
+        let ((dx, dy), (new_width, new_height)) = match edge {
+            Edge::Top => ((0, step), (width as isize, height as isize + step)),
+            Edge::Bottom => ((0, 0), (width as isize, height as isize + step)),
+            Edge::Left => ((step, 0), (width as isize + step, height as isize)),
+            Edge::Right => ((0, 0), (width as isize + step, height as isize)),
+        };
+        let (new_width, new_height) = (new_width.max(0) as usize, new_height.max(0) as usize);
+        let moved = |(x, y): (usize, usize)| {
+            let (x, y) = (x as isize + dx, y as isize + dy);
+            (x >= 0 && y >= 0 && (x as usize) < new_width && (y as usize) < new_height)
+                .then_some((x as usize, y as usize))
+        };
+
+        let mut cells = vec![Some(' '); new_width * new_height];
+        for (i, &cell) in self.cells.iter().enumerate() {
+            if let Some((x, y)) = moved((i % width, i / width)) {
+                cells[x + y * new_width] = cell;
+            }
+        }
+        let clues = self
+            .clues
+            .iter()
+            .filter_map(|clue| {
+                Some(SavedClue {
+                    head: moved(clue.head)?,
+                    ..clue.clone()
+                })
+            })
+            .collect();
+        let rebus = self
+            .rebus
+            .iter()
+            .filter_map(|rebus| {
+                Some(SavedRebus {
+                    cell: moved(rebus.cell)?,
+                    ..rebus.clone()
+                })
+            })
+            .collect();
+        let styles = self
+            .styles
+            .iter()
+            .filter_map(|style| {
+                Some(SavedStyle {
+                    cell: moved(style.cell)?,
+                    ..style.clone()
+                })
+            })
+            .collect();
+
+        let bars = self
+            .bars
+            .iter()
+            .filter_map(|bars| {
+                Some(SavedBars {
+                    cell: moved(bars.cell)?,
+                    ..bars.clone()
+                })
+            })
+            .collect();
+        let locked = self.locked.iter().filter_map(|&cell| moved(cell)).collect();
+
+        SavedCrossword {
+            width: new_width,
+            cells,
+            clues,
+            rebus,
+            styles,
+            bars,
+            locked,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the number of lettered cells and the number of white cells.
+    pub fn fill(&self) -> (usize, usize) {
+        self.cells
+            .iter()
+            .flatten()
+            .fold((0, 0), |(filled, white), &letter| {
+                (filled + usize::from(letter != ' '), white + 1)
+            })
+    }
+}
+
+impl BoolMatrix for &SavedCrossword {
+    fn rows(self) -> usize {
+        self.cells.len() / self.width
+    }
+
+    fn cols(self) -> usize {
+        self.width
+    }
+
+    fn at(self, cell: (usize, usize)) -> bool {
+        self.cell(cell).is_some()
+    }
+
+    fn barred(self, cell: (usize, usize), is_across: bool) -> bool {
+        let bars = self.bars(cell);
+        if is_across {
+            bars.right
+        } else {
+            bars.bottom
+        }
+    }
+}
+
+impl TriBoolMatrix for &SavedCrossword {
+    fn maybe_at(self, cell: (usize, usize)) -> Option<bool> {
+        self.cell(cell)
+            .map(|letter| letter == ' ' && !self.is_locked(cell))
+    }
+}
+
+impl Crossword {
+    pub(crate) fn from_saved(cx: Scope, saved: &SavedCrossword) -> Self {
+        let crossword = Self::from_grid(cx, Grid::from_saved(cx, saved));
+        crossword.set_clues(saved);
+        crossword.symmetry.set(saved.symmetry);
+        crossword
+    }
+
+    pub(crate) fn restore(&self, cx: Scope, saved: &SavedCrossword) {
+        self.grid.update(|grid| grid.restore(cx, saved));
+        self.set_clues(saved);
+        if self.symmetry.get_untracked() != saved.symmetry {
+            self.symmetry.set(saved.symmetry);
+        }
+    }
+
+    fn set_clues(&self, saved: &SavedCrossword) {
+        // `Memo::with_untracked` can't read the memo's value in leptos 0.2, so
+        // this reads it tracked instead.
+        self.answers.with(|answers| {
+            for saved_clue in saved.clues.iter() {
+                if let Some(word) = answers
+                    .get(saved_clue.head)
+                    .and_then(|head| head.word(saved_clue.is_across).as_ref())
+                {
+                    word.clue.set(saved_clue.clue.clone());
+                }
+            }
+        });
+    }
+
+    /// Reads every letter, style and clue, so calling this inside an effect
+    /// subscribes to all of them.
+    pub(crate) fn to_saved(self) -> SavedCrossword {
+        let (width, contents, bars) = self.grid.with(|grid| {
+            (
+                grid.width,
+                grid.cells
+                    .iter()
+                    .map(|cell| cell.map(|c| (c.letter.get(), c.style.get(), c.locked.get())))
+                    .collect::<Vec<_>>(),
+                grid.bars.clone(),
+            )
+        });
+
+        let clues = self.answers.with(|answers| {
+            answers
+                .answers
+                .iter()
+                .flat_map(|head| {
+                    [(true, &head.across), (false, &head.down)]
+                        .into_iter()
+                        .filter_map(move |(is_across, word)| {
+                            word.as_ref().map(|word| SavedClue {
+                                head: head.head,
+                                is_across,
+                                clue: word.clue.get(),
+                            })
+                        })
+                })
+                .collect()
+        });
+
+        let mut saved = SavedCrossword {
+            version: SCHEMA_VERSION,
+            width,
+            cells: contents
+                .iter()
+                .map(|cell| cell.as_ref().map(|_| ' '))
+                .collect(),
+            clues,
+            symmetry: self.symmetry.get(),
+            rebus: Vec::new(),
+            styles: Vec::new(),
+            bars: Vec::new(),
+            locked: Vec::new(),
+        };
+        for (i, bars) in bars.into_iter().enumerate() {
+            saved.set_bars((i % width, i / width), bars);
+        }
+        for (i, cell) in contents.into_iter().enumerate() {
+            if let Some((letters, style, locked)) = cell {
+                let coord = (i % width, i / width);
+                saved.set_letters(coord, &letters);
+                saved.set_style(coord, style);
+                saved.set_locked(coord, locked);
+            }
+        }
+        saved
+    }
+}
+
+impl Grid {
+    fn from_saved(cx: Scope, saved: &SavedCrossword) -> Self {
+        Self {
+            cells: (0..saved.cells.len())
+                .map(|i| {
+                    let coord = (i % saved.width, i / saved.width);
+                    saved.letters(coord).map(|letters| {
+                        Cell::with_contents(cx, letters, saved.style(coord), saved.is_locked(coord))
+                    })
+                })
+                .collect(),
+            width: saved.width,
+            bars: saved_bars(saved),
+        }
+    }
+
+    /// Updates the grid to match `saved`, reusing cells that stay white so
+    /// their views aren't rebuilt.
+    fn restore(&mut self, cx: Scope, saved: &SavedCrossword) {
+        if self.width != saved.width || self.cells.len() != saved.cells.len() {
+            *self = Self::from_saved(cx, saved);
+            return;
+        }
+
+        self.bars = saved_bars(saved);
+        for (i, cell) in self.cells.iter_mut().enumerate() {
+            let coord = (i % saved.width, i / saved.width);
+            let style = saved.style(coord);
+            let locked = saved.is_locked(coord);
+            match (*cell, saved.letters(coord)) {
+                (Some(cell), Some(letters)) => {
+                    if cell.letter.get_untracked() != letters {
+                        cell.letter.set(letters);
+                    }
+                    if cell.style.get_untracked() != style {
+                        cell.style.set(style);
+                    }
+                    if cell.locked.get_untracked() != locked {
+                        cell.locked.set(locked);
+                    }
+                }
+                (None, None) => (),
+                (_, letters) => {
+                    *cell = letters.map(|l| Cell::with_contents(cx, l, style, locked));
+                }
+            }
+        }
+    }
+}
+
+fn saved_bars(saved: &SavedCrossword) -> Vec<Bars> {
+    (0..saved.cells.len())
+        .map(|i| saved.bars((i % saved.width, i / saved.width)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resizing_keeps_letters_and_clues_in_place() {
+        let cells = "ABCD#FGHI".chars().map(|c| (c != '#').then_some(c));
+        let mut saved = SavedCrossword::new(3, cells.collect());
+        saved.set_clue((0, 0), true, "Top".to_owned());
+        saved.set_clue((2, 0), false, "Right".to_owned());
+
+        let taller = saved.resized(Edge::Top, true);
+        assert_eq!(taller.size(), (3, 4));
+        assert_eq!(taller.cell((0, 0)), Some(' '));
+        assert_eq!(taller.cell((0, 1)), Some('A'));
+        assert_eq!(taller.cell((1, 2)), None);
+        assert_eq!(taller.clue((0, 1), true), Some("Top"));
+
+        let narrower = saved.resized(Edge::Left, false);
+        assert_eq!(narrower.size(), (2, 3));
+        assert_eq!(narrower.cell((0, 0)), Some('B'));
+        assert_eq!(narrower.clue((0, 0), true), None);
+        assert_eq!(narrower.clue((1, 0), false), Some("Right"));
+
+        let wider = saved.resized(Edge::Right, true);
+        assert_eq!(wider.size(), (4, 3));
+        assert_eq!(wider.resized(Edge::Right, false), saved);
+    }
+}
@@ -0,0 +1,359 @@
+use std::collections::BTreeMap;
+
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{find_runs, storage, storage::SavedCrossword, BoolMatrix, Thumbnail, ThumbnailProps};
+
+/// Stock patterns, as rows where `#` is a black square.
+const BUILT_IN: &[(&str, &[&str])] = &[
+    ("Open mini", &[".....", ".....", ".....", ".....", "....."]),
+    (
+        "Staircase",
+        &[
+            "....#........",
+            "....#........",
+            "....#........",
+            "###.....#...#",
+            "......#......",
+            ".......#.....",
+            "...#.....#...",
+            ".....#.......",
+            "......#......",
+            "#...#.....###",
+            "........#....",
+            "........#....",
+            "........#....",
+        ],
+    ),
+    (
+        "Pinwheel",
+        &[
+            "....#...#....",
+            "....#........",
+            "....#........",
+            ".......#.....",
+            "........##...",
+            "##...#.......",
+            "......#......",
+            ".......#...##",
+            "...##........",
+            ".....#.......",
+            "........#....",
+            "........#....",
+            "....#...#....",
+        ],
+    ),
+    (
+        "Daily",
+        &[
+            ".........#.....",
+            ".........#.....",
+            ".........#.....",
+            "....#.....#....",
+            "##....#....#...",
+            ".....#........#",
+            "...#....#......",
+            ".......#.......",
+            "......#....#...",
+            "#........#.....",
+            "...#....#....##",
+            "....#.....#....",
+            ".....#.........",
+            ".....#.........",
+            ".....#.........",
+        ],
+    ),
+    (
+        "Daily, stepped corners",
+        &[
+            ".....#.....#...",
+            ".....#.........",
+            ".....#.........",
+            "###.....##.....",
+            ".......#.......",
+            "......#......##",
+            "......#...#....",
+            "...#.......#...",
+            "....#...#......",
+            "##......#......",
+            ".......#.......",
+            ".....##.....###",
+            ".........#.....",
+            ".........#.....",
+            "...#.....#.....",
+        ],
+    ),
+    (
+        "Themeless",
+        &[
+            ".....#.........",
+            ".....#.........",
+            ".....#.........",
+            "###.....##.....",
+            ".......#.......",
+            "......#........",
+            "..........#....",
+            "...#.......#...",
+            "....#..........",
+            "........#......",
+            ".......#.......",
+            ".....##.....###",
+            ".........#.....",
+            ".........#.....",
+            ".........#.....",
+        ],
+    ),
+    (
+        "Sunday",
+        &[
+            ".......#.....#.......",
+            ".......#.....#.......",
+            ".......#.............",
+            "...........##...#....",
+            "....##.........#.....",
+            "......#...#.......###",
+            "##...........#.......",
+            "........#.....#......",
+            "...#.......#....##...",
+            ".......#....#........",
+            "###....#.....#....###",
+            "........#....#.......",
+            "...##....#.......#...",
+            "......#.....#........",
+            ".......#...........##",
+            "###.......#...#......",
+            ".....#.........##....",
+            "....#...##...........",
+            ".............#.......",
+            ".......#.....#.......",
+            ".......#.....#.......",
+        ],
+    ),
+];
+
+/// A pattern of black squares that can be laid over a puzzle.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Template {
+    pub name: String,
+    pub width: usize,
+    /// Whether each cell is black.
+    pub cells: Vec<bool>,
+}
+
+impl Template {
+    pub fn from_rows(name: &str, rows: &[&str]) -> Self {
+        Self {
+            name: name.to_owned(),
+            width: rows.first().map_or(0, |row| row.len()),
+            cells: rows
+                .iter()
+                .flat_map(|row| row.chars().map(|c| c == '#'))
+                .collect(),
+        }
+    }
+
+    pub fn from_saved(name: &str, saved: &SavedCrossword) -> Self {
+        Self {
+            name: name.to_owned(),
+            width: saved.width,
+            cells: saved.cells.iter().map(Option::is_none).collect(),
+        }
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.cells.len() / self.width.max(1))
+    }
+
+    pub fn word_count(&self) -> usize {
+        find_runs(self).len()
+    }
+
+    /// Lays the pattern over `saved`, keeping the letters of cells that are
+    /// still white and resizing the grid to match. Locked cells stay white.
+    pub fn apply(&self, saved: &SavedCrossword) -> SavedCrossword {
+        let (width, height) = self.size();
+        let mut applied = saved.clone();
+        applied.width = width;
+        applied.cells = (0..width * height)
+            .map(|i| {
+                let cell = (i % width, i / width);
+                let is_black = self.cells[i] && !saved.is_locked(cell);
+                (!is_black).then(|| saved.cell(cell).unwrap_or(' '))
+            })
+            .collect();
+        applied.prune();
+        applied
+    }
+}
+
+impl BoolMatrix for &Template {
+    fn rows(self) -> usize {
+        self.size().1
+    }
+
+    fn cols(self) -> usize {
+        self.width
+    }
+
+    fn at(self, (x, y): (usize, usize)) -> bool {
+        x < self.width && self.cells.get(x + y * self.width) == Some(&false)
+    }
+}
+
+pub fn built_in() -> Vec<Template> {
+    BUILT_IN
+        .iter()
+        .map(|(name, rows)| Template::from_rows(name, rows))
+        .collect()
+}
+
+/// The built-in templates and the ones the user has saved.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Templates {
+    saved: RwSignal<Vec<Template>>,
+}
+
+impl Templates {
+    pub fn new(cx: Scope) -> Self {
+        let saved = create_rw_signal(cx, storage::load_templates());
+        create_effect(cx, move |_| saved.with(|t| storage::save_templates(t)));
+        Self { saved }
+    }
+
+    /// Saves `template`, replacing a saved one with the same name.
+    pub fn save(&self, template: Template) {
+        self.saved.update(|saved| {
+            saved.retain(|t| t.name != template.name);
+            saved.push(template);
+        });
+    }
+
+    pub fn remove(&self, name: &str) {
+        self.saved.update(|saved| saved.retain(|t| t.name != name));
+    }
+
+    /// Every template with whether the user saved it, grouped by size and
+    /// then word count.
+    fn grouped(&self) -> BTreeMap<(usize, usize), Vec<(Template, bool)>> {
+        let mut groups = BTreeMap::<_, Vec<_>>::new();
+        let all = built_in()
+            .into_iter()
+            .map(|t| (t, false))
+            .chain(self.saved.get().into_iter().map(|t| (t, true)));
+        for (template, is_saved) in all {
+            groups
+                .entry(template.size())
+                .or_default()
+                .push((template, is_saved));
+        }
+        for group in groups.values_mut() {
+            group.sort_by_key(|(t, _)| t.word_count());
+        }
+        groups
+    }
+}
+
+#[component]
+pub fn TemplateLibrary<S, A>(cx: Scope, saved: S, on_apply: A) -> impl IntoView
+where
+    S: Fn() -> SavedCrossword + 'static + Copy,
+    A: Fn(Template) + 'static + Copy,
+{
+    let templates = use_context::<Templates>(cx).expect("Parent did not provide templates");
+    let name = create_rw_signal(cx, String::new());
+
+    let save_current = move |_| {
+        let name = name.get_untracked();
+        let name = name.trim();
+        if !name.is_empty() {
+            templates.save(Template::from_saved(name, &saved()));
+        }
+    };
+
+    let item = move |template: Template, is_saved: bool| {
+        let label = format!("{} ({} words)", template.name, template.word_count());
+        let remove = {
+            let name = template.name.clone();
+            move |ev: web_sys::MouseEvent| {
+                ev.stop_propagation();
+                templates.remove(&name);
+            }
+        };
+        let width = template.width;
+        let cells = template.cells.clone();
+
+        view! { cx,
+            <li on:click=move |_| on_apply(template.clone())>
+                <Thumbnail width=width cells=cells/>
+                <span>{label}</span>
+                {is_saved.then(|| view! { cx, <button on:click=remove.clone()>"Delete"</button> })}
+            </li>
+        }
+    };
+
+    view! { cx,
+        <div class="templates">
+            <div class="toolbar">
+                <input
+                    type="text"
+                    placeholder="Template name"
+                    prop:value=name
+                    on:input=move |ev| name.set(event_target_value(&ev))
+                />
+                <button on:click=save_current>"Save grid as template"</button>
+            </div>
+            {move || {
+                templates
+                    .grouped()
+                    .into_iter()
+                    .map(|((width, height), group)| {
+                        view! { cx,
+                            <h4>{format!("{}×{}", width, height)}</h4>
+                            <ol class="candidates">
+                                {group
+                                    .into_iter()
+                                    .map(|(template, is_saved)| item(template, is_saved))
+                                    .collect::<Vec<_>>()}
+                            </ol>
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            }}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validity;
+
+    #[test]
+    fn built_in_templates_are_valid() {
+        for template in built_in() {
+            let validity = validity::check(&template, 3);
+            assert!(validity.is_valid(), "{}: {:?}", template.name, validity);
+        }
+    }
+
+    #[test]
+    fn apply_keeps_letters_in_white_cells() {
+        let mut saved = SavedCrossword::new(3, vec![Some(' '); 9]);
+        saved.cells[0] = Some('A');
+        saved.cells[4] = Some('B');
+        saved.cells[8] = None;
+
+        let template = Template::from_rows("Test", &["#..", "...", "..."]);
+        let applied = template.apply(&saved);
+        assert_eq!(applied.cells[0], None);
+        assert_eq!(applied.cells[4], Some('B'));
+        assert_eq!(applied.cells[8], Some(' '));
+
+        let larger = Template::from_rows("Test", &["....", "....", "....", "...."]);
+        let applied = larger.apply(&saved);
+        assert_eq!(applied.size(), (4, 4));
+        assert_eq!(applied.cells[0], Some('A'));
+        assert_eq!(applied.cells[5], Some('B'));
+    }
+}
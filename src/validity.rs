@@ -0,0 +1,230 @@
+use std::fmt::Display;
+
+use crate::BoolMatrix;
+
+/// What's wrong with a grid's pattern of black squares. Anything other than
+/// cheater squares makes the grid unusable.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Validity {
+    /// White cells cut off from the largest region of white cells.
+    pub disconnected: Vec<(usize, usize)>,
+    /// White cells that are in at most one word.
+    pub unchecked: Vec<(usize, usize)>,
+    /// Words shorter than the minimum, as `(x, y, len, is_across)` like
+    /// `find_runs`.
+    pub short: Vec<(usize, usize, usize, bool)>,
+    /// Black squares that don't change the number of words, which usually
+    /// means they only pad out a word or the grid's corner.
+    pub cheaters: Vec<(usize, usize)>,
+}
+
+impl Validity {
+    pub fn is_valid(&self) -> bool {
+        self.disconnected.is_empty() && self.unchecked.is_empty() && self.short.is_empty()
+    }
+
+    /// Whether the white cell at `cell` is part of a problem.
+    pub fn flags(&self, cell: (usize, usize)) -> bool {
+        self.disconnected.contains(&cell)
+            || self.unchecked.contains(&cell)
+            || self.short.iter().any(|&(x, y, len, is_across)| {
+                if is_across {
+                    cell.1 == y && (x..x + len).contains(&cell.0)
+                } else {
+                    cell.0 == x && (y..y + len).contains(&cell.1)
+                }
+            })
+    }
+}
+
+impl Display for Validity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let problems = [
+            (self.disconnected.len(), "cells cut off from the rest"),
+            (self.unchecked.len(), "unchecked cells"),
+            (self.short.len(), "words too short"),
+            (self.cheaters.len(), "cheater squares"),
+        ]
+        .into_iter()
+        .filter(|&(count, _)| count > 0)
+        .map(|(count, problem)| format!("{} {}", count, problem))
+        .collect::<Vec<_>>();
+
+        write!(f, "{}", problems.join(", "))
+    }
+}
+
+/// The number of white cells in a row from `(x, y)`, stepping by `(dx, dy)`
+/// and not counting `(x, y)` itself. Stops at black cells and bars.
+fn reach<M: BoolMatrix>(m: M, (x, y): (usize, usize), (dx, dy): (isize, isize)) -> usize {
+    let mut len = 0;
+    let mut previous = (x as isize, y as isize);
+    let mut cell = (x as isize + dx, y as isize + dy);
+    while cell.0 >= 0
+        && cell.1 >= 0
+        && (cell.0 as usize) < m.cols()
+        && (cell.1 as usize) < m.rows()
+        && m.at((cell.0 as usize, cell.1 as usize))
+    {
+        // The bar between two cells is kept by the one on the left or above.
+        let owner = if dx + dy > 0 { previous } else { cell };
+        if m.barred((owner.0 as usize, owner.1 as usize), dx != 0) {
+            break;
+        }
+        len += 1;
+        previous = cell;
+        cell = (cell.0 + dx, cell.1 + dy);
+    }
+    len
+}
+
+/// The cells of the largest connected region of white cells.
+fn largest_region<M: BoolMatrix>(m: M) -> Vec<bool> {
+    let (cols, rows) = (m.cols(), m.rows());
+    let mut region = vec![0; cols * rows];
+    let mut sizes = vec![0];
+
+    for start in 0..cols * rows {
+        if region[start] != 0 || !m.at((start % cols, start / cols)) {
+            continue;
+        }
+
+        let id = sizes.len();
+        let mut size = 0;
+        let mut stack = vec![start];
+        region[start] = id;
+        while let Some(i) = stack.pop() {
+            size += 1;
+            let (x, y) = (i % cols, i / cols);
+            let neighbours = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < cols).then(|| i + 1),
+                (y > 0).then(|| i - cols),
+                (y + 1 < rows).then(|| i + cols),
+            ];
+            for n in neighbours.into_iter().flatten() {
+                if region[n] == 0 && m.at((n % cols, n / cols)) {
+                    region[n] = id;
+                    stack.push(n);
+                }
+            }
+        }
+        sizes.push(size);
+    }
+
+    let largest = (1..sizes.len()).max_by_key(|&id| sizes[id]).unwrap_or(0);
+    region.into_iter().map(|id| id == largest).collect()
+}
+
+/// Checks that every white cell is connected and in an across and a down word,
+/// that no word is shorter than `min_word_len`, and finds cheater squares.
+pub fn check<M: BoolMatrix>(m: M, min_word_len: usize) -> Validity {
+    let (cols, rows) = (m.cols(), m.rows());
+    let largest = largest_region(m);
+    let mut validity = Validity::default();
+
+    for y in 0..rows {
+        for x in 0..cols {
+            let cell = (x, y);
+            let left = reach(m, cell, (-1, 0));
+            let right = reach(m, cell, (1, 0));
+            let up = reach(m, cell, (0, -1));
+            let down = reach(m, cell, (0, 1));
+
+            if m.at(cell) {
+                if !largest[x + y * cols] {
+                    validity.disconnected.push(cell);
+                }
+                if left + right == 0 || up + down == 0 {
+                    validity.unchecked.push(cell);
+                }
+                if left == 0 && (2..min_word_len).contains(&(right + 1)) {
+                    validity.short.push((x, y, right + 1, true));
+                }
+                if up == 0 && (2..min_word_len).contains(&(down + 1)) {
+                    validity.short.push((x, y, down + 1, false));
+                }
+                continue;
+            }
+
+            // Turning the square white would join or extend the words beside
+            // it, and a cheater is one where that leaves the count unchanged.
+            let words = |a: usize, b: usize| usize::from(a > 1) + usize::from(b > 1);
+            let across_change =
+                usize::from(left + right > 0) as isize - words(left, right) as isize;
+            let down_change = usize::from(up + down > 0) as isize - words(up, down) as isize;
+            if left + right > 0 && up + down > 0 && across_change + down_change == 0 {
+                validity.cheaters.push(cell);
+            }
+        }
+    }
+
+    validity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy)]
+    struct Rows<'a>(&'a [&'a str]);
+
+    impl BoolMatrix for Rows<'_> {
+        fn rows(self) -> usize {
+            self.0.len()
+        }
+
+        fn cols(self) -> usize {
+            self.0[0].len()
+        }
+
+        fn at(self, (x, y): (usize, usize)) -> bool {
+            self.0[y].as_bytes()[x] != b'#'
+        }
+    }
+
+    #[test]
+    fn open_grid_is_valid() {
+        let validity = check(Rows(&["...", "...", "..."]), 3);
+
+        assert!(validity.is_valid());
+        assert_eq!(validity, Validity::default());
+    }
+
+    #[test]
+    fn finds_disconnected_unchecked_and_short() {
+        let validity = check(Rows(&["..#..", "..#..", "#####", "....#", "....#"]), 3);
+
+        assert_eq!(
+            validity.disconnected,
+            [
+                (0, 0),
+                (1, 0),
+                (3, 0),
+                (4, 0),
+                (0, 1),
+                (1, 1),
+                (3, 1),
+                (4, 1)
+            ]
+        );
+        assert!(validity.unchecked.is_empty());
+        assert_eq!(validity.short.len(), 12);
+        assert!(!validity.is_valid());
+
+        let validity = check(Rows(&["....", "##..", "....", "...."]), 3);
+        assert_eq!(validity.unchecked, [(0, 0), (1, 0)]);
+    }
+
+    #[test]
+    fn finds_cheater_squares() {
+        let validity = check(Rows(&["#....", ".....", ".....", ".....", "....."]), 3);
+        assert_eq!(validity.cheaters, [(0, 0)]);
+
+        let validity = check(Rows(&[".....", ".....", "..#..", ".....", "....."]), 2);
+        assert!(validity.cheaters.is_empty());
+
+        let validity = check(Rows(&["....#", "...##", ".....", "....."]), 3);
+        assert_eq!(validity.cheaters, [(3, 1)]);
+    }
+}
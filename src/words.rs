@@ -0,0 +1,404 @@
+use std::collections::HashMap;
+
+use leptos::*;
+use web_sys::Event;
+
+use crate::{files, storage};
+
+pub const DEFAULT_LIST: &str = include_str!("../assets/words.txt");
+const DEFAULT_SCORE: u8 = 50;
+const LETTERS: usize = 26;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ScoredWord {
+    pub word: String,
+    pub score: u8,
+}
+
+/// Strips the spaces and punctuation that lists use in phrases like "ICE CREAM"
+/// or "O'ER", returning `None` if anything other than A-Z is left.
+pub fn normalize(word: &str) -> Option<String> {
+    let word = word
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '\'' | '.' | '_'))
+        .map(|c| c.to_ascii_uppercase())
+        .collect::<String>();
+
+    (!word.is_empty() && word.bytes().all(|b| b.is_ascii_uppercase())).then_some(word)
+}
+
+/// A set of word indices, one bit each.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct BitSet(Vec<u64>);
+
+impl BitSet {
+    fn new(len: usize) -> Self {
+        Self(vec![0; len.div_ceil(64)])
+    }
+
+    fn full(len: usize) -> Self {
+        let mut set = Self::new(len);
+        for i in 0..len {
+            set.insert(i);
+        }
+        set
+    }
+
+    fn insert(&mut self, i: usize) {
+        self.0[i / 64] |= 1 << (i % 64);
+    }
+
+    fn intersect(&mut self, other: &BitSet) {
+        for (a, b) in self.0.iter_mut().zip(other.0.iter()) {
+            *a &= b;
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().enumerate().flat_map(|(i, &block)| {
+            let mut block = block;
+            std::iter::from_fn(move || {
+                let bit = block.trailing_zeros() as usize;
+                block &= block.checked_sub(1)?;
+                Some(i * 64 + bit)
+            })
+        })
+    }
+}
+
+/// Words of a single length, best first, with a bitset of the words having
+/// each letter at each position.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct LengthIndex {
+    words: Vec<ScoredWord>,
+    letters: Vec<BitSet>,
+}
+
+impl LengthIndex {
+    fn new(len: usize, mut words: Vec<ScoredWord>) -> Self {
+        words.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.word.cmp(&b.word)));
+
+        let mut letters = vec![BitSet::new(words.len()); len * LETTERS];
+        for (i, word) in words.iter().enumerate() {
+            for (position, letter) in word.word.bytes().enumerate() {
+                letters[position * LETTERS + (letter - b'A') as usize].insert(i);
+            }
+        }
+
+        Self { words, letters }
+    }
+
+    fn matches(&self, pattern: &[Option<u8>]) -> Vec<&ScoredWord> {
+        let mut found = BitSet::full(self.words.len());
+        for (position, letter) in pattern.iter().enumerate() {
+            if let Some(letter) = letter {
+                found.intersect(&self.letters[position * LETTERS + (letter - b'A') as usize]);
+            }
+        }
+
+        found.iter().map(|i| &self.words[i]).collect()
+    }
+}
+
+/// A slot crossing the one being filled, at `position` in that slot's pattern
+/// and `offset` in the crossing one's, counted in letters so that a rebus
+/// moves the later cells along.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Crossing {
+    pub position: usize,
+    pub offset: usize,
+    pub pattern: String,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct WordList {
+    by_len: HashMap<usize, LengthIndex>,
+}
+
+impl WordList {
+    /// Reads a list in the `WORD;score` format. Lines without a score get a
+    /// middling one, and when a word appears twice its best score is kept.
+    pub fn parse(text: &str) -> Self {
+        let mut scores = HashMap::<String, u8>::new();
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (word, score) = match line.split_once(';') {
+                Some((word, score)) => (word, score.trim().parse().unwrap_or(DEFAULT_SCORE)),
+                None => (line, DEFAULT_SCORE),
+            };
+            let Some(word) = normalize(word) else {
+                continue;
+            };
+
+            let best = scores.entry(word).or_default();
+            *best = (*best).max(score);
+        }
+
+        let mut by_len = HashMap::<usize, Vec<ScoredWord>>::new();
+        for (word, score) in scores {
+            by_len
+                .entry(word.len())
+                .or_default()
+                .push(ScoredWord { word, score });
+        }
+
+        Self {
+            by_len: by_len
+                .into_iter()
+                .map(|(len, words)| (len, LengthIndex::new(len, words)))
+                .collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_len.values().map(|index| index.words.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Finds the words fitting `pattern`, best first. Any character that isn't
+    /// a letter, such as `?` or a blank cell, matches every letter.
+    pub fn matches(&self, pattern: &str) -> Vec<&ScoredWord> {
+        let pattern = pattern
+            .chars()
+            .map(|c| {
+                let c = c.to_ascii_uppercase();
+                c.is_ascii_uppercase().then_some(c as u8)
+            })
+            .collect::<Vec<_>>();
+
+        self.by_len
+            .get(&pattern.len())
+            .map(|index| index.matches(&pattern))
+            .unwrap_or_default()
+    }
+
+    /// Like `matches`, but drops words that would leave one of `crossings`
+    /// with nothing to fill it.
+    pub fn suggestions(&self, pattern: &str, crossings: &[Crossing]) -> Vec<&ScoredWord> {
+        let mut viable = HashMap::<(usize, u8), bool>::new();
+        let mut fits = |crossing: &Crossing, letter: u8| {
+            *viable
+                .entry((crossing.position, letter))
+                .or_insert_with(|| {
+                    // Offsets count characters, since a rebus in the pattern
+                    // may hold ones that aren't ASCII.
+                    let pattern = crossing
+                        .pattern
+                        .chars()
+                        .enumerate()
+                        .map(|(i, c)| {
+                            if i == crossing.offset {
+                                letter as char
+                            } else {
+                                c
+                            }
+                        })
+                        .collect::<String>();
+                    !self.matches(&pattern).is_empty()
+                })
+        };
+
+        self.matches(pattern)
+            .into_iter()
+            .filter(|word| {
+                crossings
+                    .iter()
+                    .all(|crossing| fits(crossing, word.word.as_bytes()[crossing.position]))
+            })
+            .collect()
+    }
+
+    /// Writes the list back out in the format `parse` reads.
+    pub fn to_text(&self) -> String {
+        self.by_len
+            .values()
+            .flat_map(|index| index.words.iter())
+            .map(|word| format!("{};{}\n", word.word, word.score))
+            .collect()
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        normalize(word).is_some_and(|word| !self.matches(&word).is_empty())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Dictionary {
+    pub list: RwSignal<WordList>,
+    pub name: RwSignal<String>,
+    error: RwSignal<Option<String>>,
+}
+
+impl Dictionary {
+    pub fn new(cx: Scope) -> Self {
+        let Some((name, text)) = storage::load_word_list() else {
+            return Self::from_list(cx, WordList::parse(DEFAULT_LIST));
+        };
+
+        let dictionary = Self::from_list(cx, WordList::parse(&text));
+        dictionary.name.set(name);
+        dictionary
+    }
+
+    pub fn from_list(cx: Scope, list: WordList) -> Self {
+        Self {
+            list: create_rw_signal(cx, list),
+            name: create_rw_signal(cx, "Default".to_owned()),
+            error: create_rw_signal(cx, None),
+        }
+    }
+
+    pub fn upload(&self) -> impl Fn(&str, &[u8]) + Copy {
+        let this = *self;
+        move |filename, bytes| {
+            let text = String::from_utf8_lossy(bytes);
+            let list = WordList::parse(&text);
+            if list.is_empty() {
+                this.error
+                    .set(Some(format!("{} has no usable words", filename)));
+                return;
+            }
+
+            storage::save_word_list(filename, &text);
+            this.list.set(list);
+            this.name.set(filename.to_owned());
+            this.error.set(None);
+        }
+    }
+
+    pub fn reset(&self) -> impl Fn() + Copy {
+        let this = *self;
+        move || {
+            storage::remove_word_list();
+            this.list.set(WordList::parse(DEFAULT_LIST));
+            this.name.set("Default".to_owned());
+            this.error.set(None);
+        }
+    }
+}
+
+#[component]
+pub fn WordListSettings<P>(cx: Scope, pattern: P) -> impl IntoView
+where
+    P: Fn() -> Option<String> + 'static + Copy,
+{
+    let dictionary = use_context::<Dictionary>(cx).expect("Parent did not provide dictionary");
+    let list = dictionary.list;
+    let upload = dictionary.upload();
+    let reset = dictionary.reset();
+    let error = dictionary.error;
+
+    let pick_file = move |ev: Event| {
+        spawn_local(async move {
+            if let Some((filename, bytes)) = files::read_picked_file(ev).await {
+                upload(&filename, &bytes);
+            }
+        })
+    };
+
+    let summary = move || {
+        format!(
+            "{} ({} words)",
+            dictionary.name.get(),
+            list.with(WordList::len)
+        )
+    };
+    let fits = move || {
+        pattern().map(|pattern| {
+            let count = list.with(|list| list.matches(&pattern).len());
+            format!("{} words fit {}", count, pattern.replace(' ', "?"))
+        })
+    };
+
+    view! { cx,
+        <div class="word-list toolbar">
+            <span>"Word list: " {summary}</span>
+            <label class="button">
+                "Upload list"
+                <input type="file" accept=".txt,.dict" on:change=pick_file/>
+            </label>
+            <button on:click=move |_| reset()>"Use default"</button>
+            <span class="messages">{move || error.get()}</span>
+            <span>{fits}</span>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn words(matches: Vec<&ScoredWord>) -> Vec<&str> {
+        matches.into_iter().map(|w| w.word.as_str()).collect()
+    }
+
+    #[test]
+    fn parse_normalizes_and_keeps_best_score() {
+        let list = WordList::parse("ice cream;40\nIce-Cream;70\ncafé;50\n# comment\n\nAPPLE");
+
+        assert_eq!(list.len(), 2);
+        assert_eq!(list.matches("ICECREAM")[0].score, 70);
+        assert_eq!(list.matches("APPLE")[0].score, DEFAULT_SCORE);
+    }
+
+    #[test]
+    fn matches_fill_pattern_best_first() {
+        let list = WordList::parse("ABIDE;30\nAMAZE;60\nASIDE;50\nAWAKE;40\nBRAVE;90\nAMAZED;90");
+
+        assert_eq!(words(list.matches("?A??E")), Vec::<&str>::new());
+        assert_eq!(
+            words(list.matches("A???E")),
+            ["AMAZE", "ASIDE", "AWAKE", "ABIDE"]
+        );
+        assert_eq!(words(list.matches("A?A?E")), ["AMAZE", "AWAKE"]);
+        assert_eq!(words(list.matches("AS DE")), ["ASIDE"]);
+        assert_eq!(words(list.matches("a?ide")), ["ASIDE", "ABIDE"]);
+        assert!(list.matches("???").is_empty());
+    }
+
+    #[test]
+    fn suggestions_keep_crossings_fillable() {
+        let list = WordList::parse("CAT;50\nCOT;60\nTOE;40\nAXE;30");
+        let crossings = [Crossing {
+            position: 1,
+            offset: 0,
+            pattern: "?XE".to_owned(),
+        }];
+
+        assert_eq!(words(list.suggestions("C?T", &[])), ["COT", "CAT"]);
+        assert_eq!(words(list.suggestions("C?T", &crossings)), ["CAT"]);
+    }
+
+    #[test]
+    fn to_text_parses_back_to_the_same_list() {
+        let list = WordList::parse("ice cream;40\nAPPLE\nCAT;90");
+
+        assert_eq!(WordList::parse(&list.to_text()), list);
+    }
+
+    #[test]
+    fn bundled_list_covers_lengths_with_varied_scores() {
+        let list = WordList::parse(DEFAULT_LIST);
+
+        for len in 3..=15 {
+            let words = list.matches(&"?".repeat(len));
+            assert!(!words.is_empty(), "no {len}-letter words");
+
+            let scores = words.iter().map(|w| w.score).collect::<HashSet<_>>();
+            assert!(
+                scores.len() > 2,
+                "{len}-letter scores don't vary: {scores:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use std::{cell::RefCell, rc::Rc};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+use web_sys::{ErrorEvent, MessageEvent, Worker};
+
+use crate::{
+    autofill::autofill_with,
+    generate::{Candidate, GeneratorConfig, GridGenerator},
+    storage::SavedCrossword,
+    words::WordList,
+};
+
+/// The script trunk builds from `src/bin/worker.rs`, which loads its wasm.
+const WORKER_SCRIPT: &str = "./worker_loader.js";
+
+/// Long-running work sent to the worker as JSON.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum Job {
+    Generate {
+        saved: SavedCrossword,
+        config: GeneratorConfig,
+        seed: Option<u64>,
+    },
+    Autofill {
+        saved: SavedCrossword,
+        /// The word list in the `WORD;score` format, since the parsed one
+        /// can't be sent.
+        words: String,
+    },
+}
+
+impl Job {
+    /// The crossword the job works on.
+    pub fn saved(&self) -> &SavedCrossword {
+        match self {
+            Job::Generate { saved, .. } | Job::Autofill { saved, .. } => saved,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Job::Generate { .. } => "Generating",
+            Job::Autofill { .. } => "Autofilling",
+        }
+    }
+}
+
+/// The result of a `Job`, or the best one so far.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum Outcome {
+    /// The best distinct patterns, best first.
+    Blacks {
+        candidates: Vec<Candidate>,
+        seed: u64,
+    },
+    Fill(SavedCrossword),
+    Unfillable {
+        message: String,
+        /// The head and direction of the slot to look at first.
+        slot: Option<((usize, usize), bool)>,
+    },
+}
+
+/// What the worker sends back while a `Job` runs.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum Report {
+    Progress {
+        iteration: usize,
+        score: f32,
+        best: Option<Outcome>,
+    },
+    Done(Outcome),
+}
+
+pub fn generate(
+    saved: &SavedCrossword,
+    config: GeneratorConfig,
+    seed: Option<u64>,
+    mut on_progress: impl FnMut(Report),
+) -> Outcome {
+    let mut generator = GridGenerator::with_config(saved, config).with_symmetry(saved.symmetry);
+    if let Some(seed) = seed {
+        generator = generator.with_seed(seed);
+    }
+    let seed = generator.seed();
+
+    generator.place_blacks_with(|iteration, score, cells| {
+        on_progress(Report::Progress {
+            iteration,
+            score,
+            best: Some(Outcome::Blacks {
+                candidates: vec![Candidate::new(saved.width, cells, score)],
+                seed,
+            }),
+        })
+    });
+
+    Outcome::Blacks {
+        candidates: generator.candidates(),
+        seed,
+    }
+}
+
+pub fn fill(
+    saved: &SavedCrossword,
+    list: &WordList,
+    mut on_progress: impl FnMut(Report),
+) -> Outcome {
+    let result = autofill_with(saved, list, |iteration, score, filled| {
+        on_progress(Report::Progress {
+            iteration,
+            score: score as f32,
+            best: Some(Outcome::Fill(filled)),
+        })
+    });
+
+    match result {
+        Ok(filled) => Outcome::Fill(filled),
+        Err(e) => Outcome::Unfillable {
+            message: format!("Autofill {}", e),
+            slot: e.slots.first().map(|entry| (entry.head, entry.is_across)),
+        },
+    }
+}
+
+/// Runs `job` to the end, which is what the worker does with each message.
+pub fn run(job: Job, mut on_report: impl FnMut(Report)) {
+    let outcome = match job {
+        Job::Generate {
+            saved,
+            config,
+            seed,
+        } => generate(&saved, config, seed, &mut on_report),
+        Job::Autofill { saved, words } => fill(&saved, &WordList::parse(&words), &mut on_report),
+    };
+    on_report(Report::Done(outcome));
+}
+
+/// Where a `Running` job sends its outcome, or why it failed, once.
+type Sender = Rc<RefCell<Option<oneshot::Sender<Result<Outcome, String>>>>>;
+
+fn finish(sender: &Sender, result: Result<Outcome, String>) {
+    if let Some(sender) = sender.borrow_mut().take() {
+        _ = sender.send(result);
+    }
+}
+
+/// A `Job` running in a worker. Dropping it stops the worker, so the receiver
+/// returned by `start` never gets an outcome.
+pub struct Running {
+    worker: Worker,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_error: Closure<dyn FnMut(ErrorEvent)>,
+    _on_message_error: Closure<dyn FnMut(MessageEvent)>,
+}
+
+impl Running {
+    /// Starts `job` in a new worker. The receiver gets the outcome, or an
+    /// error if the worker fails to load, panics or sends something
+    /// unreadable.
+    pub fn start(
+        job: &Job,
+        on_progress: impl Fn(Report) + 'static,
+    ) -> Result<(Self, oneshot::Receiver<Result<Outcome, String>>), JsValue> {
+        let worker = Worker::new(WORKER_SCRIPT)?;
+        let (sender, receiver) = oneshot::channel();
+        let sender: Sender = Rc::new(RefCell::new(Some(sender)));
+
+        let on_message = Closure::<dyn FnMut(MessageEvent)>::new({
+            let sender = sender.clone();
+            move |ev: MessageEvent| {
+                let Some(json) = ev.data().as_string() else {
+                    return;
+                };
+                match serde_json::from_str(&json) {
+                    Ok(Report::Done(outcome)) => finish(&sender, Ok(outcome)),
+                    Ok(report) => on_progress(report),
+                    Err(e) => finish(&sender, Err(format!("unreadable message: {}", e))),
+                }
+            }
+        });
+        worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        let on_error = Closure::<dyn FnMut(ErrorEvent)>::new({
+            let sender = sender.clone();
+            move |ev: ErrorEvent| finish(&sender, Err(ev.message()))
+        });
+        worker.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+
+        let on_message_error = Closure::<dyn FnMut(MessageEvent)>::new(move |_| {
+            finish(&sender, Err("a message couldn't be received".to_owned()))
+        });
+        worker.set_onmessageerror(Some(on_message_error.as_ref().unchecked_ref()));
+
+        let json = serde_json::to_string(job).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        worker.post_message(&JsValue::from_str(&json))?;
+
+        Ok((
+            Self {
+                worker,
+                _on_message: on_message,
+                _on_error: on_error,
+                _on_message_error: on_message_error,
+            },
+            receiver,
+        ))
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.worker.terminate();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_ends_with_the_outcome() {
+        let saved = SavedCrossword::new(3, vec![Some(' '); 9]);
+        let words = "HAT;50\nERA;50\nMEN;50\nHEM;50\nARE;50\nTAN;50".to_owned();
+
+        let mut reports = Vec::new();
+        run(Job::Autofill { saved, words }, |report| {
+            reports.push(report)
+        });
+
+        let Some(Report::Done(Outcome::Fill(filled))) = reports.last() else {
+            panic!("expected a fill, got {:?}", reports.last());
+        };
+        assert_eq!(filled.fill(), (9, 9));
+    }
+
+    #[test]
+    fn jobs_round_trip_through_json() {
+        let job = Job::Generate {
+            saved: SavedCrossword::blank(),
+            config: GeneratorConfig::default(),
+            seed: Some(7),
+        };
+        let json = serde_json::to_string(&job).unwrap();
+
+        assert_eq!(serde_json::from_str::<Job>(&json).unwrap(), job);
+    }
+}